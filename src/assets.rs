@@ -0,0 +1,178 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use sdl2::{image::LoadTexture, render::{Texture, TextureCreator}, video::WindowContext};
+
+use crate::engine::{AnimatedSprite, Animation, Audio, Font, Sound, Sprite, Track};
+use crate::error::{Error, Result};
+
+//Everything the game loads, by id
+const TEXTURES: [(&str, &str); 8] = [
+    ("back", "assets/sprites/back.png"),
+    ("font", "assets/sprites/font.png"),
+    ("ghost", "assets/sprites/ghost.png"),
+    ("jumpscare", "assets/sprites/jumpscare.png"),
+    ("light", "assets/sprites/light.png"),
+    ("objects", "assets/sprites/objects.png"),
+    ("pixel", "assets/sprites/pixel.png"),
+    ("player", "assets/sprites/player.png")
+];
+
+//Frame data for the texture with the same id
+const ANIMATIONS: [(&str, &str); 2] = [
+    ("jumpscare", "assets/sprites/jumpscare.anim"),
+    ("player", "assets/sprites/player.anim")
+];
+
+const SOUNDS: [(&str, &str); 18] = [
+    ("ambient", "assets/sounds/ambient2.ogg"),
+    ("ambient_tense", "assets/sounds/ambient.ogg"),
+    ("extinguish", "assets/sounds/extingushing.wav"),
+    ("ghost", "assets/sounds/ghost.ogg"),
+    ("jumpscare", "assets/sounds/jumpscare.wav"),
+    ("match", "assets/sounds/match.wav"),
+    ("scary", "assets/sounds/scary.wav"),
+    ("strange", "assets/sounds/strange.wav"),
+    ("tip", "assets/sounds/tip.wav"),
+
+    //Footsteps, three takes per surface
+    ("step_dirt_1", "assets/sounds/step_dirt_1.wav"),
+    ("step_dirt_2", "assets/sounds/step_dirt_2.wav"),
+    ("step_dirt_3", "assets/sounds/step_dirt_3.wav"),
+    ("step_leaves_1", "assets/sounds/step_leaves_1.wav"),
+    ("step_leaves_2", "assets/sounds/step_leaves_2.wav"),
+    ("step_leaves_3", "assets/sounds/step_leaves_3.wav"),
+    ("step_wood_1", "assets/sounds/step_wood_1.wav"),
+    ("step_wood_2", "assets/sounds/step_wood_2.wav"),
+    ("step_wood_3", "assets/sounds/step_wood_3.wav")
+];
+
+//Closed captions, sounds that aren't here play without one
+const CAPTIONS: [(&str, &str); 6] = [
+    ("extinguish", "[candle dies]"),
+    ("ghost", "[ghostly wail]"),
+    ("jumpscare", "[shriek]"),
+    ("match", "[match strikes]"),
+    ("scary", "[eerie sting]"),
+    ("strange", "[strange noise]")
+];
+
+const MUSIC: [(&str, &str); 1] = [
+    ("fire_ambient", "assets/sounds/fireambient.wav")
+];
+
+pub struct Assets<'a>
+{
+    textures: HashMap<&'static str, Rc<RefCell<Texture<'a>>>>,
+    animations: HashMap<&'static str, Rc<Animation>>,
+    sounds: HashMap<&'static str, Sound>,
+    music: HashMap<&'static str, Track>,
+    missing: Vec<Error> /* Files replaced with a placeholder */
+}
+
+#[allow(dead_code)]
+impl<'a> Assets<'a>
+{
+    //Loads everything once. Missing files get a placeholder and are listed in missing(), only failing to make a placeholder is an error
+    pub fn load(texture_creator: &'a TextureCreator<WindowContext>, audio: &Audio) -> Result<Assets<'a>>
+    {
+        let mut assets = Assets { textures: HashMap::new(), animations: HashMap::new(), sounds: HashMap::new(), music: HashMap::new(), missing: Vec::new() };
+
+        for (id, path) in TEXTURES
+        {
+            let texture = match texture_creator.load_texture(path)
+            {
+                Ok(texture) => texture,
+                Err(err) =>
+                {
+                    assets.missing.push(Error::asset(path, err));
+                    Sprite::placeholder_texture(texture_creator)?
+                }
+            };
+
+            assets.textures.insert(id, Rc::new(RefCell::new(texture)));
+        }
+
+        for (id, path) in ANIMATIONS
+        {
+            //Without frame data the whole sheet is one frame
+            let animation = Animation::from_file(path).unwrap_or_else(|err|
+            {
+                assets.missing.push(err);
+                let query = assets.textures[id].borrow().query();
+                Animation { width: query.width, height: query.height, clips: HashMap::new() }
+            });
+
+            assets.animations.insert(id, Rc::new(animation));
+        }
+
+        for (id, path) in SOUNDS
+        {
+            let mut sound = match audio.load_sound(path)
+            {
+                Ok(sound) => sound,
+                Err(err) =>
+                {
+                    assets.missing.push(err);
+                    audio.silent_sound()?
+                }
+            };
+
+            sound.caption = CAPTIONS.iter().find(|(sound_id, _)| *sound_id == id).map(|(_, caption)| *caption);
+            assets.sounds.insert(id, sound);
+        }
+
+        for (id, path) in MUSIC
+        {
+            let music = match audio.load_music(path)
+            {
+                Ok(music) => music,
+                Err(err) =>
+                {
+                    assets.missing.push(err);
+                    audio.silent_music()?
+                }
+            };
+
+            assets.music.insert(id, music);
+        }
+
+        Ok(assets)
+    }
+
+    pub fn missing(&self) -> &[Error]
+    {
+        &self.missing
+    }
+
+    pub fn texture(&self, id: &str) -> Rc<RefCell<Texture<'a>>>
+    {
+        Rc::clone(&self.textures[id])
+    }
+
+    //A new sprite drawing from the shared texture
+    pub fn sprite(&self, id: &str) -> Sprite<'a>
+    {
+        Sprite::from_shared(self.texture(id))
+    }
+
+    pub fn animated_sprite(&self, id: &str, clip: &str) -> AnimatedSprite<'a>
+    {
+        AnimatedSprite::new(self.sprite(id), Rc::clone(&self.animations[id]), clip)
+    }
+
+    //The 3x5 font every screen draws its text with
+    pub fn font(&self) -> Font<'a>
+    {
+        Font::new(self.sprite("font"), 3, 5)
+    }
+
+    pub fn sound(&self, id: &str) -> &Sound
+    {
+        &self.sounds[id]
+    }
+
+    pub fn music(&self, id: &str) -> &Track
+    {
+        &self.music[id]
+    }
+}
@@ -0,0 +1,113 @@
+use sdl2::{rect::{Point, Rect}, pixels::Color, render::Canvas, video::Window};
+
+use crate::assets::Assets;
+use crate::engine::{Align, Audio, Caption, Font, Sprite};
+
+//Most captions shown at once, the newest win
+const MAX_LINES: usize = 2;
+
+//Captions quieter than this weren't heard
+const MIN_GAIN: f64 = 0.05;
+
+//Where a sound came from, as an arrow next to its caption
+#[derive(Clone, Copy, PartialEq)]
+enum Arrow
+{
+    Up,
+    Right,
+    Down,
+    Left
+}
+
+impl Arrow
+{
+    fn from_angle(angle: f64) -> Arrow
+    {
+        match ((angle + 45.0).rem_euclid(360.0) / 90.0) as i32
+        {
+            0 => Arrow::Up,
+            1 => Arrow::Right,
+            2 => Arrow::Down,
+            _ => Arrow::Left
+        }
+    }
+}
+
+//Closed captions for the sounds the audio layer played, along the bottom of the screen
+pub struct Captions<'a>
+{
+    font: Font<'a>,
+    back: Sprite<'a>,
+    pixel: Sprite<'a>
+}
+
+impl<'a> Captions<'a>
+{
+    pub fn new(assets: &Assets<'a>) -> Captions<'a>
+    {
+        let mut back = assets.sprite("pixel");
+        back.set_color_mod(Color::BLACK);
+        back.alpha = 200;
+
+        let mut pixel = assets.sprite("pixel");
+        pixel.set_color_mod(Color::WHITE);
+
+        Captions { font: assets.font(), back, pixel }
+    }
+
+    pub fn draw(&mut self, canvas: &mut Canvas<Window>, audio: &Audio)
+    {
+        let heard: Vec<&Caption> = audio.captions().iter().filter(|caption| caption.gain >= MIN_GAIN).collect();
+        let line_height = self.font.line_height() + 2;
+
+        //Newest at the bottom, just above the stamina bars
+        for (i, caption) in heard.iter().rev().take(MAX_LINES).enumerate()
+        {
+            let y = 53 - i as i32 * line_height;
+            let arrow = caption.angle.map(Arrow::from_angle);
+
+            //Arrows take 3 pixels and a gap, on the side the sound came from
+            let text_width = self.font.text_width(caption.text);
+            let width = text_width + if arrow.is_some() { 4 } else { 0 };
+            let mut x = 32 - width / 2;
+
+            self.back.dst = Rect::new(x - 1, y - 1, (width + 2) as u32, (self.font.glyph_height + 2) as u32);
+            self.back.draw(canvas);
+
+            match arrow
+            {
+                Some(Arrow::Right) =>
+                {
+                    self.draw_arrow(canvas, Arrow::Right, Point::new(x + text_width + 1, y));
+                },
+                Some(arrow) =>
+                {
+                    self.draw_arrow(canvas, arrow, Point::new(x, y));
+                    x += 4;
+                },
+                None => {}
+            }
+
+            self.font.draw_text(canvas, Point::new(x, y), caption.text, Color::WHITE, Align::Left);
+        }
+    }
+
+    //A 3x5 arrow pointing that way, pos is its top left
+    fn draw_arrow(&mut self, canvas: &mut Canvas<Window>, arrow: Arrow, pos: Point)
+    {
+        let (x, y) = (pos.x(), pos.y());
+        let rects = match arrow
+        {
+            Arrow::Left => [Rect::new(x, y + 2, 1, 1), Rect::new(x + 1, y + 1, 1, 3), Rect::new(x + 2, y, 1, 5)],
+            Arrow::Right => [Rect::new(x + 2, y + 2, 1, 1), Rect::new(x + 1, y + 1, 1, 3), Rect::new(x, y, 1, 5)],
+            Arrow::Up => [Rect::new(x + 1, y, 1, 1), Rect::new(x, y + 1, 3, 1), Rect::new(x + 1, y + 2, 1, 3)],
+            Arrow::Down => [Rect::new(x + 1, y + 4, 1, 1), Rect::new(x, y + 3, 3, 1), Rect::new(x + 1, y, 1, 3)]
+        };
+
+        for rect in rects
+        {
+            self.pixel.dst = rect;
+            self.pixel.draw(canvas);
+        }
+    }
+}
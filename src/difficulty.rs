@@ -0,0 +1,210 @@
+//Most of any one thing a map gets
+pub const MAX_PLACED: usize = 64;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Preset
+{
+    Easy,
+    Normal,
+    Hard,
+    Nightmare,
+    Custom
+}
+
+impl Preset
+{
+    pub const ALL: [Preset; 5] = [Preset::Easy, Preset::Normal, Preset::Hard, Preset::Nightmare, Preset::Custom];
+
+    pub fn name(self) -> &'static str
+    {
+        match self
+        {
+            Preset::Easy => "easy",
+            Preset::Normal => "normal",
+            Preset::Hard => "hard",
+            Preset::Nightmare => "nightmare",
+            Preset::Custom => "custom"
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Preset>
+    {
+        Preset::ALL.into_iter().find(|p| p.name() == name)
+    }
+
+    pub fn index(self) -> usize
+    {
+        Preset::ALL.iter().position(|p| *p == self).unwrap()
+    }
+}
+
+#[derive(Clone)]
+pub struct Difficulty
+{
+    //Ghost
+    pub ghost_speed: f64,
+    pub ghost_speed_max: f64,
+    pub ghost_speed_step: f64, /* Added every time a candle is lit */
+
+    //Matches
+    pub match_speed: f64, /* Progress per frame while holding Z */
+    pub match_waste: f64, /* Letting go of Z past this progress wastes the match */
+    pub matches: u32,
+    pub match_boxes: usize,
+    pub matches_per_box: u32,
+
+    //Darkness
+    pub light_radius: f64, /* Of the candle in hand, before it flickers */
+    pub candle_burn: f64, /* Wick burnt per frame */
+    pub candles_min: usize,
+    pub candles_max: usize,
+
+    //Fear
+    pub fear_dark: f64,
+    pub fear_ghost: f64,
+    pub fear_calm: f64,
+
+    //Sprint
+    pub sprint_speed: f64,
+    pub exhausted_speed: f64,
+    pub stamina_drain: f64,
+    pub stamina_regen: f64,
+    pub step_noise: f64, /* Radius the ghost hears footsteps from */
+    pub sprint_noise: f64, /* Same while running */
+
+    //Hiding
+    pub hiding_spots: usize,
+    pub breath_drain: f64,
+    pub breath_regen: f64,
+    pub ghost_sight: f64 /* The ghost notices the player hiding within this distance */
+}
+
+impl Difficulty
+{
+    pub fn from_preset(preset: Preset) -> Difficulty
+    {
+        match preset
+        {
+            Preset::Easy => Difficulty
+            {
+                ghost_speed: 0.75, ghost_speed_max: 3.0, ghost_speed_step: 0.35,
+                match_speed: 0.015, match_waste: 0.6,
+                matches: 12, match_boxes: 6, matches_per_box: 4,
+                light_radius: 27.5, candle_burn: 0.00014, candles_min: 6, candles_max: 9,
+                fear_dark: 0.00015, fear_ghost: 0.002, fear_calm: 0.005,
+                sprint_speed: 2.0, exhausted_speed: 0.8, stamina_drain: 0.004, stamina_regen: 0.004, step_noise: 24.0, sprint_noise: 48.0,
+                hiding_spots: 10, breath_drain: 0.003, breath_regen: 0.004, ghost_sight: 32.0
+            },
+            Preset::Normal | Preset::Custom => Difficulty
+            {
+                ghost_speed: 1.0, ghost_speed_max: 4.0, ghost_speed_step: 0.5,
+                match_speed: 0.01, match_waste: 0.5,
+                matches: 8, match_boxes: 5, matches_per_box: 3,
+                light_radius: 22.5, candle_burn: 0.00019, candles_min: 8, candles_max: 12,
+                fear_dark: 0.00025, fear_ghost: 0.003, fear_calm: 0.004,
+                sprint_speed: 1.8, exhausted_speed: 0.7, stamina_drain: 0.006, stamina_regen: 0.003, step_noise: 32.0, sprint_noise: 64.0,
+                hiding_spots: 8, breath_drain: 0.004, breath_regen: 0.003, ghost_sight: 40.0
+            },
+            Preset::Hard => Difficulty
+            {
+                ghost_speed: 1.25, ghost_speed_max: 5.0, ghost_speed_step: 0.6,
+                match_speed: 0.008, match_waste: 0.4,
+                matches: 6, match_boxes: 4, matches_per_box: 3,
+                light_radius: 19.0, candle_burn: 0.00022, candles_min: 10, candles_max: 14,
+                fear_dark: 0.00035, fear_ghost: 0.004, fear_calm: 0.003,
+                sprint_speed: 1.7, exhausted_speed: 0.6, stamina_drain: 0.007, stamina_regen: 0.0025, step_noise: 40.0, sprint_noise: 80.0,
+                hiding_spots: 6, breath_drain: 0.005, breath_regen: 0.0025, ghost_sight: 48.0
+            },
+            Preset::Nightmare => Difficulty
+            {
+                ghost_speed: 1.5, ghost_speed_max: 6.0, ghost_speed_step: 0.75,
+                match_speed: 0.006, match_waste: 0.3,
+                matches: 4, match_boxes: 3, matches_per_box: 2,
+                light_radius: 15.0, candle_burn: 0.00028, candles_min: 12, candles_max: 16,
+                fear_dark: 0.0005, fear_ghost: 0.005, fear_calm: 0.0025,
+                sprint_speed: 1.6, exhausted_speed: 0.5, stamina_drain: 0.009, stamina_regen: 0.002, step_noise: 48.0, sprint_noise: 96.0,
+                hiding_spots: 4, breath_drain: 0.006, breath_regen: 0.002, ghost_sight: 56.0
+            }
+        }
+    }
+
+    //Set a tuning value by its settings file key, returns false if the key is unknown
+    pub fn set(&mut self, key: &str, value: &str) -> bool
+    {
+        let value: f64 = match value.parse()
+        {
+            Ok(value) => value,
+            Err(_) => return false
+        };
+
+        match key
+        {
+            "ghost_speed" => self.ghost_speed = value,
+            "ghost_speed_max" => self.ghost_speed_max = value,
+            "ghost_speed_step" => self.ghost_speed_step = value,
+            "match_speed" => self.match_speed = value,
+            "match_waste" => self.match_waste = value,
+            "matches" => self.matches = value as u32,
+            "match_boxes" => self.match_boxes = value as usize,
+            "matches_per_box" => self.matches_per_box = value as u32,
+            "light_radius" => self.light_radius = value,
+            "candle_burn" => self.candle_burn = value,
+            "candles_min" => self.candles_min = value as usize,
+            "candles_max" => self.candles_max = value as usize,
+            "fear_dark" => self.fear_dark = value,
+            "fear_ghost" => self.fear_ghost = value,
+            "fear_calm" => self.fear_calm = value,
+            "sprint_speed" => self.sprint_speed = value,
+            "exhausted_speed" => self.exhausted_speed = value,
+            "stamina_drain" => self.stamina_drain = value,
+            "stamina_regen" => self.stamina_regen = value,
+            "step_noise" => self.step_noise = value,
+            "sprint_noise" => self.sprint_noise = value,
+            "hiding_spots" => self.hiding_spots = value as usize,
+            "breath_drain" => self.breath_drain = value,
+            "breath_regen" => self.breath_regen = value,
+            "ghost_sight" => self.ghost_sight = value,
+            _ => return false
+        }
+
+        //Keep the candle range valid for gen_range
+        self.candles_min = self.candles_min.clamp(1, MAX_PLACED - 1);
+        self.candles_max = self.candles_max.clamp(self.candles_min + 1, MAX_PLACED);
+
+        //There has to be room on the map for everything
+        self.hiding_spots = self.hiding_spots.min(MAX_PLACED);
+        self.match_boxes = self.match_boxes.min(MAX_PLACED);
+        true
+    }
+
+    pub fn values(&self) -> Vec<(&'static str, f64)>
+    {
+        vec![
+            ("ghost_speed", self.ghost_speed),
+            ("ghost_speed_max", self.ghost_speed_max),
+            ("ghost_speed_step", self.ghost_speed_step),
+            ("match_speed", self.match_speed),
+            ("match_waste", self.match_waste),
+            ("matches", self.matches as f64),
+            ("match_boxes", self.match_boxes as f64),
+            ("matches_per_box", self.matches_per_box as f64),
+            ("light_radius", self.light_radius),
+            ("candle_burn", self.candle_burn),
+            ("candles_min", self.candles_min as f64),
+            ("candles_max", self.candles_max as f64),
+            ("fear_dark", self.fear_dark),
+            ("fear_ghost", self.fear_ghost),
+            ("fear_calm", self.fear_calm),
+            ("sprint_speed", self.sprint_speed),
+            ("exhausted_speed", self.exhausted_speed),
+            ("stamina_drain", self.stamina_drain),
+            ("stamina_regen", self.stamina_regen),
+            ("step_noise", self.step_noise),
+            ("sprint_noise", self.sprint_noise),
+            ("hiding_spots", self.hiding_spots as f64),
+            ("breath_drain", self.breath_drain),
+            ("breath_regen", self.breath_regen),
+            ("ghost_sight", self.ghost_sight)
+        ]
+    }
+}
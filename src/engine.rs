@@ -1,31 +1,28 @@
-use std::collections::HashSet;
+use std::{cell::{RefCell, RefMut}, collections::{HashMap, HashSet}, fs, rc::Rc};
 
-use sdl2::{Sdl, mixer::{Sdl2MixerContext, DEFAULT_CHANNELS, Music, Chunk, AUDIO_S16LSB}, image::{Sdl2ImageContext, LoadTexture}, VideoSubsystem, render::{Canvas, Texture, TextureCreator, BlendMode}, video::{Window, WindowContext}, EventPump, event::Event, keyboard::Keycode, rect::{Rect, Point}, pixels::Color};
+use rand::Rng;
+use sdl2::{Sdl, mixer::{Sdl2MixerContext, DEFAULT_CHANNELS, Channel, Music, Chunk, AUDIO_S16LSB}, image::{Sdl2ImageContext, LoadTexture}, VideoSubsystem, render::{Canvas, Texture, TextureCreator, BlendMode}, video::{Window, WindowContext}, EventPump, event::Event, keyboard::Keycode, rect::{Rect, Point}, pixels::{Color, PixelFormatEnum}, surface::Surface};
 use spin_sleep::LoopHelper;
 
+use crate::error::{Error, Result};
+
 #[allow(dead_code)]
 pub struct Init
 {
     sdl: Sdl,
-    sdl_mixer: Sdl2MixerContext,
     sdl_image: Sdl2ImageContext
 }
 
 impl Init
 {
-    pub fn new() -> Init
+    pub fn new() -> Result<Init>
     {
-        let context = sdl2::init().expect("Failed to init SDL!");
-
-        //Init SDL_mixer context
-        sdl2::mixer::open_audio(44_100, AUDIO_S16LSB, DEFAULT_CHANNELS, 1024).expect("Failed to open audio!");
-        let mixer_context = sdl2::mixer::init(sdl2::mixer::InitFlag::OGG).expect("Failed to init SDL_mixer!");
-        sdl2::mixer::allocate_channels(6);
+        let context = sdl2::init().map_err(Error::Init)?;
 
         //Init SDL_image context
-        let image_context = sdl2::image::init(sdl2::image::InitFlag::PNG).expect("Failed to init SDL_image!");
+        let image_context = sdl2::image::init(sdl2::image::InitFlag::PNG).map_err(Error::Init)?;
 
-        Init { sdl: context, sdl_mixer: mixer_context, sdl_image: image_context }
+        Ok(Init { sdl: context, sdl_image: image_context })
     }
 
     pub fn context(&self) -> &Sdl
@@ -34,6 +31,34 @@ impl Init
     }
 }
 
+#[derive(Clone, Copy, PartialEq)]
+pub enum Renderer
+{
+    Auto, /* Accelerated, falling back to software */
+    Accelerated,
+    Software
+}
+
+impl Renderer
+{
+    pub const ALL: [Renderer; 3] = [Renderer::Auto, Renderer::Accelerated, Renderer::Software];
+
+    pub fn name(self) -> &'static str
+    {
+        match self
+        {
+            Renderer::Auto => "auto",
+            Renderer::Accelerated => "accelerated",
+            Renderer::Software => "software"
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Renderer>
+    {
+        Renderer::ALL.into_iter().find(|r| r.name() == name)
+    }
+}
+
 pub struct Video
 {
     video: VideoSubsystem,
@@ -43,18 +68,53 @@ pub struct Video
 #[allow(dead_code)]
 impl Video
 {
-    pub fn new(ctx: &Sdl, w: u32, h: u32) -> Video
+    pub fn new(ctx: &Sdl, w: u32, h: u32, renderer: Renderer) -> Result<Video>
     {
-        let video = ctx.video().expect("Failed to create VideoSubsystem!");
+        let video = ctx.video().map_err(Error::Video)?;
+
+        let chain = match renderer
+        {
+            Renderer::Auto => vec![Renderer::Accelerated, Renderer::Software],
+            renderer => vec![renderer]
+        };
 
-        //Create window
-        let window = video.window("Game", w, h).vulkan().resizable().position_centered().build().expect("Failed to open window!");
+        //Use the first renderer that works
+        let mut last_err = Error::Video(String::from("No renderer to try"));
+        for renderer in chain
+        {
+            match Video::create_canvas(&video, w, h, renderer)
+            {
+                Ok(mut canvas) =>
+                {
+                    eprintln!("Using the {} renderer ({})", renderer.name(), canvas.info().name);
+                    canvas.set_logical_size(w, h).map_err(|err| Error::Video(err.to_string()))?;
 
-        //Try to create accelerated renderer
-        let mut canvas = window.into_canvas().accelerated().build().expect("Failed to create renderer!");
-        canvas.set_logical_size(w, h).expect("Failed to set logical size!");
+                    return Ok(Video { video, canvas });
+                },
+                Err(err) =>
+                {
+                    eprintln!("Warning: {} renderer unavailable: {}", renderer.name(), err);
+                    last_err = err;
+                }
+            }
+        }
 
-        Video { video: video, canvas: canvas }
+        Err(last_err)
+    }
+
+    //The canvas takes the window, so every attempt needs a new one
+    fn create_canvas(video: &VideoSubsystem, w: u32, h: u32, renderer: Renderer) -> Result<Canvas<Window>>
+    {
+        let window = video.window("Game", w, h).resizable().position_centered().build().map_err(|err| Error::Video(err.to_string()))?;
+
+        let builder = window.into_canvas();
+        let builder = match renderer
+        {
+            Renderer::Software => builder.software(),
+            _ => builder.accelerated()
+        };
+
+        builder.build().map_err(|err| Error::Video(err.to_string()))
     }
 
     pub fn canvas(&self) -> &Canvas<Window>
@@ -79,24 +139,453 @@ impl Video
 
 }
 
-pub struct Audio;
+//Mixer channels shared by every bus
+pub const CHANNELS: usize = 16;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Bus
+{
+    Music,
+    Ambience,
+    Sfx,
+    Voice
+}
+
+impl Bus
+{
+    pub const ALL: [Bus; 4] = [Bus::Music, Bus::Ambience, Bus::Sfx, Bus::Voice];
+
+    pub fn name(self) -> &'static str
+    {
+        match self
+        {
+            Bus::Music => "music",
+            Bus::Ambience => "ambience",
+            Bus::Sfx => "sfx",
+            Bus::Voice => "voice"
+        }
+    }
+
+    pub fn index(self) -> usize
+    {
+        Bus::ALL.iter().position(|b| *b == self).unwrap()
+    }
+}
+
+//When every channel is busy a sound steals one from an equal or lower priority sound, or isn't played
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority
+{
+    Low,
+    Normal,
+    High
+}
+
+//How a positioned sound fades out towards its range
+#[allow(dead_code)]
+#[derive(Clone, Copy)]
+pub enum Attenuation
+{
+    Linear,
+    Inverse, /* Drops off quickly close up, then tails off */
+    Smooth
+}
+
+impl Attenuation
+{
+    //Gain at a distance given as a fraction of the range
+    pub fn gain(self, t: f64) -> f64
+    {
+        let t = t.clamp(0.0, 1.0);
+        match self
+        {
+            Attenuation::Linear => 1.0 - t,
+            Attenuation::Inverse => (1.0 - t) / (1.0 + 4.0 * t),
+            Attenuation::Smooth => 1.0 - t * t * (3.0 - 2.0 * t)
+        }
+    }
+}
+
+//How much of a sound gets through a wall
+pub const OCCLUSION: f64 = 0.35;
+
+//The game runs the same without a sound device, the Null backend just does nothing
+pub enum Backend
+{
+    Mixer(Sdl2MixerContext),
+    Null
+}
+
+//A loaded sound effect, no chunk with the Null backend
+pub struct Sound
+{
+    chunk: Option<Chunk>,
+    pub caption: Option<&'static str> /* Shown when it plays, see Caption */
+}
+
+//A loaded music track, None with the Null backend
+pub struct Track(Option<Music<'static>>);
+
+//Frames a caption stays up once its sound has stopped
+pub const CAPTION_FRAMES: u32 = 120;
+
+//Text for a sound that was played, kept even without a sound device so it can still be read
+#[derive(Clone, Copy)]
+pub struct Caption
+{
+    pub text: &'static str,
+    pub angle: Option<f64>, /* Degrees clockwise from up, None if it isn't positioned */
+    pub gain: f64, /* How loud it reaches the listener, 0 to 1 */
+    pub frames: u32, /* Left before it goes */
+
+    id: u64 /* Voice that played it */
+}
+
+//A playing sound, goes stale once it ends or its channel is stolen
+#[derive(Clone, Copy)]
+pub struct Voice
+{
+    channel: Option<usize>,
+    id: u64
+}
+
+impl Voice
+{
+    pub const NONE: Voice = Voice { channel: None, id: 0 };
+}
+
+//What a mixer channel was last given
+#[derive(Clone, Copy)]
+struct Slot
+{
+    id: u64,
+    bus: Bus,
+    priority: Priority,
+    volume: f64 /* Before the bus volume */
+}
+
+pub struct Audio
+{
+    backend: Backend,
+    volumes: [f64; 4], /* Per bus, 0 to 1 */
+    slots: [Slot; CHANNELS],
+    next_id: u64,
+    captions: Vec<Caption> /* Oldest first */
+}
 
 #[allow(dead_code)]
 impl Audio
 {
-    pub fn new() -> Audio
+    //Falls back to the Null backend if disabled or the device can't be opened
+    pub fn new(enabled: bool) -> Audio
+    {
+        let backend = if enabled
+        {
+            match Audio::open()
+            {
+                Ok(context) => Backend::Mixer(context),
+                Err(err) =>
+                {
+                    eprintln!("Warning: {}, running without sound", err);
+                    Backend::Null
+                }
+            }
+        }
+        else
+        {
+            eprintln!("Audio disabled");
+            Backend::Null
+        };
+
+        let slot = Slot { id: 0, bus: Bus::Sfx, priority: Priority::Low, volume: 1.0 };
+        Audio { backend, volumes: [1.0; 4], slots: [slot; CHANNELS], next_id: 1, captions: Vec::new() }
+    }
+
+    fn open() -> Result<Sdl2MixerContext>
+    {
+        sdl2::mixer::open_audio(44_100, AUDIO_S16LSB, DEFAULT_CHANNELS, 1024).map_err(Error::Init)?;
+        let context = sdl2::mixer::init(sdl2::mixer::InitFlag::OGG).map_err(Error::Init)?;
+        sdl2::mixer::allocate_channels(CHANNELS as i32);
+
+        Ok(context)
+    }
+
+    pub fn enabled(&self) -> bool
+    {
+        matches!(self.backend, Backend::Mixer(_))
+    }
+
+    pub fn load_music(&self, path: &str) -> Result<Track>
+    {
+        match self.backend
+        {
+            Backend::Mixer(_) => Music::from_file(path).map(|music| Track(Some(music))).map_err(|err| Error::asset(path, err)),
+            Backend::Null => Ok(Track(None))
+        }
+    }
+
+    pub fn load_sound(&self, path: &str) -> Result<Sound>
+    {
+        match self.backend
+        {
+            Backend::Mixer(_) => Chunk::from_file(path).map(|chunk| Sound { chunk: Some(chunk), caption: None }).map_err(|err| Error::asset(path, err)),
+            Backend::Null => Ok(Sound { chunk: None, caption: None })
+        }
+    }
+
+    //Stands in for a sound that couldn't be loaded
+    pub fn silent_sound(&self) -> Result<Sound>
+    {
+        match self.backend
+        {
+            Backend::Mixer(_) => Chunk::from_raw_buffer(vec![0i16; 256].into_boxed_slice()).map(|chunk| Sound { chunk: Some(chunk), caption: None }).map_err(Error::Init),
+            Backend::Null => Ok(Sound { chunk: None, caption: None })
+        }
+    }
+
+    //Same for music, a tenth of a second of silence as a WAV
+    pub fn silent_music(&self) -> Result<Track>
+    {
+        if !self.enabled()
+        {
+            return Ok(Track(None));
+        }
+
+        let samples = 4410u32;
+        let mut wav = Vec::new();
+        wav.extend_from_slice(b"RIFF");
+        wav.extend_from_slice(&(36 + samples * 2).to_le_bytes());
+        wav.extend_from_slice(b"WAVEfmt ");
+        wav.extend_from_slice(&16u32.to_le_bytes());
+        wav.extend_from_slice(&1u16.to_le_bytes()); /* PCM */
+        wav.extend_from_slice(&1u16.to_le_bytes()); /* Mono */
+        wav.extend_from_slice(&44_100u32.to_le_bytes());
+        wav.extend_from_slice(&(44_100u32 * 2).to_le_bytes());
+        wav.extend_from_slice(&2u16.to_le_bytes());
+        wav.extend_from_slice(&16u16.to_le_bytes());
+        wav.extend_from_slice(b"data");
+        wav.extend_from_slice(&(samples * 2).to_le_bytes());
+        wav.resize(wav.len() + samples as usize * 2, 0);
+
+        //Music borrows its data for good, it's only made once
+        Music::from_static_bytes(Box::leak(wav.into_boxed_slice())).map(|music| Track(Some(music))).map_err(Error::Init)
+    }
+
+    //Plays on a free channel, stealing one if it has to. The voice is never playing if it couldn't be played,
+    //but its caption still goes up
+    pub fn play(&mut self, sound: &Sound, bus: Bus, priority: Priority, loops: i32) -> Voice
+    {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        //The same caption again replaces the old one rather than stacking up
+        if let Some(text) = sound.caption
+        {
+            self.captions.retain(|caption| caption.text != text);
+            self.captions.push(Caption { text, angle: None, gain: 1.0, frames: CAPTION_FRAMES, id });
+        }
+
+        let chunk = match (&self.backend, &sound.chunk)
+        {
+            (Backend::Mixer(_), Some(chunk)) => chunk,
+            _ => return Voice { channel: None, id }
+        };
+
+        let channel = match self.free_channel(priority)
+        {
+            Some(channel) => channel,
+            None => return Voice { channel: None, id }
+        };
+
+        self.slots[channel] = Slot { id, bus, priority, volume: 1.0 };
+
+        //Clear whatever the last sound on this channel left behind
+        let mixer = Channel(channel as i32);
+        let _ = mixer.set_position(0, 0);
+        mixer.set_volume(self.channel_volume(channel));
+
+        match mixer.play(chunk, loops)
+        {
+            Ok(_) => Voice { channel: Some(channel), id },
+            Err(_) => Voice { channel: None, id }
+        }
+    }
+
+    //A silent channel, or else the lowest priority one that's been playing the longest
+    fn free_channel(&self, priority: Priority) -> Option<usize>
+    {
+        if let Some(channel) = (0..CHANNELS).find(|c| !Channel(*c as i32).is_playing())
+        {
+            return Some(channel);
+        }
+
+        (0..CHANNELS)
+            .filter(|c| self.slots[*c].priority <= priority)
+            .min_by_key(|c| (self.slots[*c].priority, self.slots[*c].id))
+    }
+
+    //The voice's channel if it's still playing it
+    fn channel(&self, voice: Voice) -> Option<usize>
+    {
+        let channel = voice.channel?;
+        (self.slots[channel].id == voice.id && Channel(channel as i32).is_playing()).then_some(channel)
+    }
+
+    fn channel_volume(&self, channel: usize) -> i32
+    {
+        let slot = &self.slots[channel];
+        (slot.volume * self.volumes[slot.bus.index()] * 128.0).round() as i32
+    }
+
+    pub fn is_playing(&self, voice: Voice) -> bool
+    {
+        self.channel(voice).is_some()
+    }
+
+    pub fn stop(&self, voice: Voice)
+    {
+        if let Some(channel) = self.channel(voice)
+        {
+            Channel(channel as i32).halt();
+        }
+    }
+
+    //0 to 1, scaled by the bus volume
+    pub fn set_volume(&mut self, voice: Voice, volume: f64)
+    {
+        if let Some(channel) = self.channel(voice)
+        {
+            self.slots[channel].volume = volume.clamp(0.0, 1.0);
+            Channel(channel as i32).set_volume(self.channel_volume(channel));
+        }
+    }
+
+    pub fn set_panning(&self, voice: Voice, left: u8, right: u8)
+    {
+        if let Some(channel) = self.channel(voice)
+        {
+            let _ = Channel(channel as i32).set_panning(left, right);
+        }
+    }
+
+    //Places a sound around the listener, north is up. Occluded sounds are muffled down to OCCLUSION.
+    //Its caption follows along even once it's stopped playing
+    pub fn set_spatial(&mut self, voice: Voice, listener: &PointF, source: &PointF, range: f64, attenuation: Attenuation, occluded: bool)
+    {
+        let mut gain = attenuation.gain(listener.distance(source) / range);
+        if occluded
+        {
+            gain *= OCCLUSION;
+        }
+
+        //Clockwise from straight ahead, none when it's right on the listener
+        let (dx, dy) = (source.x - listener.x, source.y - listener.y);
+        let angle = (dx.abs() >= 1.0 || dy.abs() >= 1.0).then(|| dx.atan2(-dy).to_degrees().rem_euclid(360.0));
+
+        if let Some(caption) = self.captions.iter_mut().find(|caption| voice.id != 0 && caption.id == voice.id)
+        {
+            caption.angle = angle;
+            caption.gain = gain;
+        }
+
+        let channel = match self.channel(voice)
+        {
+            Some(channel) => Channel(channel as i32),
+            None => return
+        };
+
+        //SDL_mixer's distance is 0 for right here to 255 for as far as it goes
+        let distance = ((1.0 - gain) * 255.0).round() as u8;
+        let _ = match angle
+        {
+            Some(angle) => channel.set_position(angle as i16, distance),
+            None => channel.set_distance(distance)
+        };
+    }
+
+    pub fn captions(&self) -> &[Caption]
     {
-        Audio {}
+        &self.captions
     }
 
-    pub fn load_music(&self, path: &str) -> Music
+    //Call once a frame, captions only start counting down once their sound has stopped
+    pub fn update_captions(&mut self)
     {
-        Music::from_file(path).expect("Failed to load music file!")
+        for i in 0..self.captions.len()
+        {
+            let voice = Voice { channel: self.slots.iter().position(|slot| slot.id == self.captions[i].id), id: self.captions[i].id };
+            if !self.is_playing(voice)
+            {
+                self.captions[i].frames = self.captions[i].frames.saturating_sub(1);
+            }
+        }
+
+        self.captions.retain(|caption| caption.frames > 0);
+    }
+
+    pub fn clear_captions(&mut self)
+    {
+        self.captions.clear();
+    }
+
+    pub fn bus_volume(&self, bus: Bus) -> f64
+    {
+        self.volumes[bus.index()]
+    }
+
+    //Applies to everything already playing on the bus too
+    pub fn set_bus_volume(&mut self, bus: Bus, volume: f64)
+    {
+        self.volumes[bus.index()] = volume.clamp(0.0, 1.0);
+
+        if !self.enabled()
+        {
+            return;
+        }
+
+        for channel in (0..CHANNELS).filter(|c| self.slots[*c].bus == bus)
+        {
+            Channel(channel as i32).set_volume(self.channel_volume(channel));
+        }
+
+        if bus == Bus::Music
+        {
+            Music::set_volume((self.volumes[bus.index()] * 128.0).round() as i32);
+        }
     }
 
-    pub fn load_sound(&self, path: &str) -> Chunk
+    //Music always goes on the music bus
+    pub fn play_music(&self, track: &Track, loops: i32)
     {
-        Chunk::from_file(path).expect("Failed to load WAV file!")
+        if let Some(music) = &track.0
+        {
+            Music::set_volume((self.bus_volume(Bus::Music) * 128.0).round() as i32);
+            let _ = music.play(loops);
+        }
+    }
+
+    pub fn fade_out(&self, voice: Voice, ms: i32)
+    {
+        if let Some(channel) = self.channel(voice)
+        {
+            Channel(channel as i32).fade_out(ms);
+        }
+    }
+
+    pub fn fade_out_music(&self, ms: i32)
+    {
+        if self.enabled()
+        {
+            let _ = Music::fade_out(ms);
+        }
+    }
+
+    pub fn halt_music(&self)
+    {
+        if self.enabled()
+        {
+            Music::halt();
+        }
     }
 }
 
@@ -113,11 +602,11 @@ pub struct EventLoop
 #[allow(dead_code)]
 impl EventLoop 
 {
-    pub fn new(ctx: &Sdl, target_fps: u32) -> EventLoop
+    pub fn new(ctx: &Sdl, target_fps: u32) -> Result<EventLoop>
     {
         let loop_helper = LoopHelper::builder().build_with_target_rate(target_fps);
-        let event_pump = ctx.event_pump().unwrap();
-        EventLoop { loop_helper, event_pump, keys: HashSet::new(), prev_keys: HashSet::new(), delta: 0.0 }
+        let event_pump = ctx.event_pump().map_err(Error::Init)?;
+        Ok(EventLoop { loop_helper, event_pump, keys: HashSet::new(), prev_keys: HashSet::new(), delta: 0.0 })
     }
 
     pub fn loop_start(&mut self) -> bool
@@ -127,17 +616,16 @@ impl EventLoop
         //Event handling
         for event in self.event_pump.poll_iter() 
         {
-            match event
+            if let Event::Quit { .. } = event
             {
-                Event::Quit { .. } => return false,
-                _ => {}
+                return false;
             }
         }
 
         self.prev_keys = self.keys.clone();
         self.keys = self.event_pump.keyboard_state().pressed_scancodes().filter_map(Keycode::from_scancode).collect();
 
-        return true;
+        true
     }
 
     pub fn is_key_down(&self, key: Keycode) -> bool
@@ -163,7 +651,9 @@ impl EventLoop
 
 pub struct Sprite<'a>
 {
-    texture: Texture<'a>,
+    texture: Rc<RefCell<Texture<'a>>>, /* Can be shared with other sprites, see Assets */
+    color: Color,
+    blend: BlendMode,
     
     pub dst: Rect,
     pub src: Rect,
@@ -178,36 +668,37 @@ pub struct Sprite<'a>
 #[allow(dead_code)]
 impl<'a> Sprite<'a>
 {
-    pub fn from_file(texture_creator: &'a TextureCreator<WindowContext>, path: &'a str) -> Sprite<'a>
+    pub fn from_file(texture_creator: &'a TextureCreator<WindowContext>, path: &str) -> Result<Sprite<'a>>
     {
-        let texture = texture_creator.load_texture(path).expect("Failed to load texture!");
-
-        let query = texture.query();
-        let w = query.width;
-        let h = query.height;
+        let texture = texture_creator.load_texture(path).map_err(|err| Error::asset(path, err))?;
+        Ok(Sprite::from_texture(texture))
+    }
 
-        Sprite 
-        { 
-            texture, 
-            dst: Rect::new(0, 0, w, h), 
-            src: Rect::new(0, 0, w, h), 
-            pivot: Point::new(0, 0), 
-            angle: 0.0, 
-            alpha: 255,
-            hflip: false, 
-            vflip: false
-        }
+    //Magenta square that stands in for a texture that couldn't be loaded
+    pub fn placeholder_texture(texture_creator: &'a TextureCreator<WindowContext>) -> Result<Texture<'a>>
+    {
+        let mut surface = Surface::new(16, 16, PixelFormatEnum::RGB24).map_err(Error::Video)?;
+        surface.fill_rect(None, Color::MAGENTA).map_err(Error::Video)?;
+        texture_creator.create_texture_from_surface(&surface).map_err(|err| Error::Video(err.to_string()))
     }
 
     pub fn from_texture(texture: Texture<'a>) -> Sprite<'a>
     {
-        let query = texture.query();
+        Sprite::from_shared(Rc::new(RefCell::new(texture)))
+    }
+
+    pub fn from_shared(texture: Rc<RefCell<Texture<'a>>>) -> Sprite<'a>
+    {
+        let query = texture.borrow().query();
         let w = query.width;
         let h = query.height;
+        let blend = texture.borrow().blend_mode();
 
         Sprite 
         { 
             texture, 
+            color: Color::WHITE,
+            blend,
             dst: Rect::new(0, 0, w, h), 
             src: Rect::new(0, 0, w, h), 
             pivot: Point::new(0, 0), 
@@ -220,34 +711,578 @@ impl<'a> Sprite<'a>
 
     pub fn draw(&mut self, canvas: &mut Canvas<Window>)
     {
-        self.texture.set_alpha_mod(self.alpha);
+        let texture = self.apply_mods();
         
-        canvas.copy_ex(&self.texture, self.src, self.dst, self.angle, self.pivot, self.hflip, self.vflip).expect("Failed to copy texture!");
+        //A sprite that can't be drawn is left out of the frame rather than ending the game
+        if let Err(err) = canvas.copy_ex(&texture, self.src, self.dst, self.angle, self.pivot, self.hflip, self.vflip)
+        {
+            eprintln!("Warning: couldn't draw a sprite: {}", err);
+        }
     }
 
     pub fn draw_offset(&mut self, canvas: &mut Canvas<Window>, offset: Point)
     {
-        self.texture.set_alpha_mod(self.alpha);
+        let texture = self.apply_mods();
 
-        let mut new_rect = self.dst.clone();
+        let mut new_rect = self.dst;
         new_rect.set_x(new_rect.x() + offset.x());
         new_rect.set_y(new_rect.y() + offset.y());
-        canvas.copy_ex(&self.texture, self.src, new_rect, self.angle, self.pivot, self.hflip, self.vflip).expect("Failed to copy texture!");
+        if let Err(err) = canvas.copy_ex(&texture, self.src, new_rect, self.angle, self.pivot, self.hflip, self.vflip)
+        {
+            eprintln!("Warning: couldn't draw a sprite: {}", err);
+        }
     }
 
     pub fn set_blend_mod(&mut self, mode: BlendMode)
     {
-        self.texture.set_blend_mode(mode);
+        self.blend = mode;
     }
 
     pub fn set_color_mod(&mut self, mode: Color)
     {
-        self.texture.set_color_mod(mode.r, mode.g, mode.b);
+        self.color = mode;
+    }
+
+    //The texture may be shared, so this sprite's mods go on right before it's drawn
+    fn apply_mods(&self) -> RefMut<'_, Texture<'a>>
+    {
+        let mut texture = self.texture.borrow_mut();
+        texture.set_alpha_mod(self.alpha);
+        texture.set_color_mod(self.color.r, self.color.g, self.color.b);
+        texture.set_blend_mode(self.blend);
+        texture
+    }
+}
+
+pub struct Frame
+{
+    pub index: i32, /* Position in the sprite sheet, left to right */
+    pub duration: u32, /* Game frames it's shown for */
+    pub event: Option<String> /* Fired when the frame comes up */
+}
+
+pub struct Clip
+{
+    pub frames: Vec<Frame>,
+    pub looping: bool /* One-shot clips stop on their last frame */
+}
+
+pub struct Animation
+{
+    pub width: u32,
+    pub height: u32,
+    pub clips: HashMap<String, Clip>
+}
+
+impl Animation
+{
+    pub fn from_file(path: &str) -> Result<Animation>
+    {
+        let text = fs::read_to_string(path).map_err(|err| Error::asset(path, err))?;
+        Ok(Animation::parse(&text))
+    }
+
+    //"size=w h" and "clip.name=loop|once index:duration[:event] ..." lines, see assets/sprites/player.anim
+    pub fn parse(text: &str) -> Animation
+    {
+        let mut animation = Animation { width: 0, height: 0, clips: HashMap::new() };
+
+        for line in text.lines()
+        {
+            let (key, value) = match line.split_once('=')
+            {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => continue
+            };
+
+            if key == "size"
+            {
+                let mut size = value.split_whitespace().filter_map(|v| v.parse().ok());
+                animation.width = size.next().unwrap_or(0);
+                animation.height = size.next().unwrap_or(0);
+            }
+            else if let Some(name) = key.strip_prefix("clip.")
+            {
+                let mut words = value.split_whitespace();
+                let looping = words.next() == Some("loop");
+                let frames = words.filter_map(|word|
+                {
+                    let mut parts = word.split(':');
+                    let index = parts.next()?.parse().ok()?;
+                    let duration: u32 = parts.next()?.parse().ok()?;
+                    Some(Frame { index, duration: duration.max(1), event: parts.next().map(String::from) })
+                }).collect();
+
+                animation.clips.insert(name.to_string(), Clip { frames, looping });
+            }
+        }
+
+        animation
+    }
+}
+
+pub struct AnimatedSprite<'a>
+{
+    pub sprite: Sprite<'a>,
+    pub animation: Rc<Animation>,
+
+    clip: String,
+    frame: usize,
+    time: u32, /* Game frames the current frame has been up */
+    entered: bool /* The current frame's event hasn't been fired yet */
+}
+
+#[allow(dead_code)]
+impl<'a> AnimatedSprite<'a>
+{
+    pub fn new(mut sprite: Sprite<'a>, animation: Rc<Animation>, clip: &str) -> AnimatedSprite<'a>
+    {
+        sprite.src.set_width(animation.width);
+        sprite.src.set_height(animation.height);
+        sprite.dst.set_width(animation.width);
+        sprite.dst.set_height(animation.height);
+
+        let mut animated = AnimatedSprite { sprite, animation, clip: String::new(), frame: 0, time: 0, entered: false };
+        animated.play(clip);
+        animated
+    }
+
+    //Loads the sheet along with the .anim file next to it
+    pub fn from_file(texture_creator: &'a TextureCreator<WindowContext>, path: &str, clip: &str) -> Result<AnimatedSprite<'a>>
+    {
+        let animation = Rc::new(Animation::from_file(&path.replace(".png", ".anim"))?);
+        Ok(AnimatedSprite::new(Sprite::from_file(texture_creator, path)?, animation, clip))
+    }
+
+    //Switches clip, playing the one that's already on does nothing
+    pub fn play(&mut self, clip: &str)
+    {
+        if self.clip != clip
+        {
+            self.clip = clip.to_string();
+            self.restart();
+        }
+    }
+
+    pub fn restart(&mut self)
+    {
+        self.frame = 0;
+        self.time = 0;
+        self.entered = true;
+        self.set_src();
+    }
+
+    //Steps one game frame, returns the event of the frame that came up if any
+    pub fn update(&mut self) -> Option<&str>
+    {
+        let (len, looping, duration) = match self.animation.clips.get(&self.clip)
+        {
+            Some(clip) if !clip.frames.is_empty() => (clip.frames.len(), clip.looping, clip.frames[self.frame].duration),
+            _ => return None
+        };
+
+        if self.entered
+        {
+            self.entered = false;
+            return self.event();
+        }
+
+        self.time += 1;
+        if self.time < duration
+        {
+            return None;
+        }
+
+        if self.frame + 1 < len
+        {
+            self.frame += 1;
+        }
+        else if looping
+        {
+            self.frame = 0;
+        }
+        else
+        {
+            self.time = duration;
+            return None;
+        }
+
+        self.time = 0;
+        self.set_src();
+        self.event()
+    }
+
+    //One-shot clip that has shown its last frame for its full duration
+    pub fn finished(&self) -> bool
+    {
+        match self.animation.clips.get(&self.clip)
+        {
+            Some(clip) => !clip.looping && self.frame + 1 >= clip.frames.len() && clip.frames.last().is_none_or(|frame| self.time >= frame.duration),
+            None => true
+        }
     }
+
+    pub fn draw(&mut self, canvas: &mut Canvas<Window>)
+    {
+        self.sprite.draw(canvas);
+    }
+
+    fn event(&self) -> Option<&str>
+    {
+        self.animation.clips.get(&self.clip).and_then(|clip| clip.frames.get(self.frame)).and_then(|frame| frame.event.as_deref())
+    }
+
+    fn set_src(&mut self)
+    {
+        if let Some(frame) = self.animation.clips.get(&self.clip).and_then(|clip| clip.frames.get(self.frame))
+        {
+            self.sprite.src.set_x(frame.index * self.animation.width as i32);
+        }
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Clone, Copy, PartialEq)]
+pub enum Align
+{
+    Left,
+    Center,
+    Right
 }
 
+//Fixed width bitmap font, the sheet has ASCII 32 to 127 in rows of 16 with a pixel of space after every glyph
+pub struct Font<'a>
+{
+    sprite: Sprite<'a>,
+
+    pub glyph_width: i32,
+    pub glyph_height: i32,
+    pub spacing: i32 /* Between glyphs, and between lines */
+}
+
+#[allow(dead_code)]
+impl<'a> Font<'a>
+{
+    pub fn new(sprite: Sprite<'a>, glyph_width: i32, glyph_height: i32) -> Font<'a>
+    {
+        Font { sprite, glyph_width, glyph_height, spacing: 1 }
+    }
+
+    pub fn line_height(&self) -> i32
+    {
+        self.glyph_height + self.spacing
+    }
+
+    pub fn text_width(&self, text: &str) -> i32
+    {
+        let count = text.chars().count() as i32;
+        (count * (self.glyph_width + self.spacing) - self.spacing).max(0)
+    }
+
+    //Draws one line, pos is the top of the text and its left, middle or right depending on align
+    pub fn draw_text(&mut self, canvas: &mut Canvas<Window>, pos: Point, text: &str, color: Color, align: Align)
+    {
+        let x = match align
+        {
+            Align::Left => pos.x(),
+            Align::Center => pos.x() - self.text_width(text) / 2,
+            Align::Right => pos.x() - self.text_width(text)
+        };
+
+        self.sprite.set_color_mod(color);
+        let (w, h) = (self.glyph_width, self.glyph_height);
+
+        for (i, c) in text.chars().enumerate()
+        {
+            //Anything the sheet doesn't have shows up as a question mark
+            let code = if c.is_ascii() && c >= ' ' { c as i32 } else { '?' as i32 };
+            if code == ' ' as i32
+            {
+                continue;
+            }
+
+            let index = code - 32;
+            self.sprite.src = Rect::new(index % 16 * (w + 1), index / 16 * (h + 1), w as u32, h as u32);
+            self.sprite.dst = Rect::new(x + i as i32 * (w + self.spacing), pos.y(), w as u32, h as u32);
+            self.sprite.draw(canvas);
+        }
+    }
+
+    //Splits text into lines that fit the width, breaking at spaces where it can. Newlines always break
+    pub fn wrap(&self, text: &str, width: i32) -> Vec<String>
+    {
+        let max = ((width + self.spacing) / (self.glyph_width + self.spacing)).max(1) as usize;
+        let mut lines = Vec::new();
+
+        for paragraph in text.split('\n')
+        {
+            let mut line = String::new();
+            for word in paragraph.split_whitespace()
+            {
+                let mut word = word.to_string();
+
+                //Words longer than a whole line get cut up
+                while word.chars().count() > max
+                {
+                    if !line.is_empty()
+                    {
+                        lines.push(std::mem::take(&mut line));
+                    }
+
+                    let cut = word.char_indices().nth(max).map(|(i, _)| i).unwrap_or(word.len());
+                    let rest = word.split_off(cut);
+                    lines.push(word);
+                    word = rest;
+                }
+
+                if !line.is_empty() && line.chars().count() + 1 + word.chars().count() > max
+                {
+                    lines.push(std::mem::take(&mut line));
+                }
+
+                if !line.is_empty()
+                {
+                    line.push(' ');
+                }
+                line.push_str(&word);
+            }
+
+            lines.push(line);
+        }
+
+        lines
+    }
+
+    //Draws the text wrapped to the width, returns how tall it came out
+    pub fn draw_wrapped(&mut self, canvas: &mut Canvas<Window>, pos: Point, text: &str, width: i32, color: Color, align: Align) -> i32
+    {
+        let lines = self.wrap(text, width);
+        for (i, line) in lines.iter().enumerate()
+        {
+            self.draw_text(canvas, Point::new(pos.x(), pos.y() + i as i32 * self.line_height()), line, color, align);
+        }
+
+        lines.len() as i32 * self.line_height()
+    }
+}
+
+//Wrapped text clipped to a box, scrolls when it's too long to fit
+pub struct TextBox
+{
+    pub rect: Rect,
+    pub color: Color,
+    pub align: Align,
+    pub scroll: f64, /* Pixels scrolled down */
+    pub speed: f64, /* Pixels a frame it scrolls by itself, 0 to only scroll by hand */
+    pub pause: u32, /* Frames it waits at either end when scrolling by itself */
+
+    lines: Vec<String>,
+    line_height: i32,
+    wait: u32
+}
+
+#[allow(dead_code)]
+impl TextBox
+{
+    pub fn new(font: &Font, rect: Rect, text: &str) -> TextBox
+    {
+        let mut text_box = TextBox
+        {
+            rect,
+            color: Color::WHITE,
+            align: Align::Left,
+            scroll: 0.0,
+            speed: 0.0,
+            pause: 60,
+            lines: Vec::new(),
+            line_height: font.line_height(),
+            wait: 0
+        };
+        text_box.set_text(font, text);
+        text_box
+    }
+
+    pub fn set_text(&mut self, font: &Font, text: &str)
+    {
+        self.lines = font.wrap(text, self.rect.width() as i32);
+        self.line_height = font.line_height();
+        self.scroll = 0.0;
+        self.wait = self.pause;
+    }
+
+    //How far it can scroll before the last line is at the bottom
+    pub fn max_scroll(&self) -> f64
+    {
+        (self.lines.len() as i32 * self.line_height - self.rect.height() as i32).max(0) as f64
+    }
+
+    pub fn scroll_by(&mut self, pixels: f64)
+    {
+        self.scroll = (self.scroll + pixels).clamp(0.0, self.max_scroll());
+        self.wait = self.pause;
+    }
+
+    //Scrolls to the end, waits, then jumps back to the top
+    pub fn update(&mut self)
+    {
+        if self.speed <= 0.0 || self.max_scroll() <= 0.0
+        {
+            return;
+        }
+
+        if self.wait > 0
+        {
+            self.wait -= 1;
+        }
+        else if self.scroll >= self.max_scroll()
+        {
+            self.scroll = 0.0;
+            self.wait = self.pause;
+        }
+        else
+        {
+            self.scroll = (self.scroll + self.speed).min(self.max_scroll());
+            if self.scroll >= self.max_scroll()
+            {
+                self.wait = self.pause;
+            }
+        }
+    }
+
+    pub fn draw(&self, canvas: &mut Canvas<Window>, font: &mut Font)
+    {
+        let x = match self.align
+        {
+            Align::Left => self.rect.left(),
+            Align::Center => self.rect.left() + self.rect.width() as i32 / 2,
+            Align::Right => self.rect.right()
+        };
+
+        canvas.set_clip_rect(self.rect);
+        for (i, line) in self.lines.iter().enumerate()
+        {
+            let y = self.rect.top() + i as i32 * self.line_height - self.scroll as i32;
+            if y + self.line_height > self.rect.top() && y < self.rect.bottom()
+            {
+                font.draw_text(canvas, Point::new(x, y), line, self.color, self.align);
+            }
+        }
+        canvas.set_clip_rect(None);
+    }
+}
+
+#[derive(Clone, Copy)]
 pub struct PointF
 {
     pub x: f64,
     pub y: f64
-}
\ No newline at end of file
+}
+
+impl PointF
+{
+    pub fn distance(&self, other: &PointF) -> f64
+    {
+        ((self.x - other.x).powi(2) + (self.y - other.y).powi(2)).sqrt()
+    }
+}
+
+pub struct Camera
+{
+    pub pos: PointF, /* Top left of the view in world space, before shake */
+    pub smoothing: f64, /* Fraction of the way to the target moved each frame */
+    pub look_ahead: f64, /* How far ahead of the target it looks */
+    pub trauma: f64, /* 0 to 1, shake is trauma squared */
+    pub max_shake: f64,
+
+    target: PointF,
+    size: PointF,
+    bounds: Rect,
+    shake: Point
+}
+
+#[allow(dead_code)]
+impl Camera
+{
+    pub fn new(w: u32, h: u32, bounds: Rect) -> Camera
+    {
+        Camera
+        {
+            pos: PointF { x: 0.0, y: 0.0 },
+            smoothing: 0.1,
+            look_ahead: 8.0,
+            trauma: 0.0,
+            max_shake: 4.0,
+            target: PointF { x: 0.0, y: 0.0 },
+            size: PointF { x: w as f64, y: h as f64 },
+            bounds,
+            shake: Point::new(0, 0)
+        }
+    }
+
+    //Point to keep in the middle of the view, pushed ahead in the facing direction
+    pub fn follow(&mut self, target: PointF, facing: PointF)
+    {
+        self.target = PointF
+        {
+            x: target.x + facing.x * self.look_ahead - self.size.x / 2.0,
+            y: target.y + facing.y * self.look_ahead - self.size.y / 2.0
+        };
+    }
+
+    //Jump straight to the target without smoothing
+    pub fn snap(&mut self)
+    {
+        self.pos = self.clamped(self.target);
+    }
+
+    pub fn add_trauma(&mut self, amount: f64)
+    {
+        self.trauma = (self.trauma + amount).clamp(0.0, 1.0);
+    }
+
+    pub fn update(&mut self)
+    {
+        let target = self.clamped(self.target);
+        self.pos.x += (target.x - self.pos.x) * self.smoothing;
+        self.pos.y += (target.y - self.pos.y) * self.smoothing;
+
+        //Shake
+        let shake = self.trauma * self.trauma * self.max_shake;
+        let mut rand = rand::thread_rng();
+        self.shake = Point::new(rand.gen_range(-shake..=shake).round() as i32, rand.gen_range(-shake..=shake).round() as i32);
+        self.trauma = (self.trauma - 0.02).max(0.0);
+    }
+
+    //Top left of the view this frame, shake included
+    pub fn origin(&self) -> Point
+    {
+        Point::new(self.pos.x.round() as i32 + self.shake.x(), self.pos.y.round() as i32 + self.shake.y())
+    }
+
+    pub fn world_to_screen(&self, pos: &PointF) -> Point
+    {
+        let origin = self.origin();
+        Point::new(pos.x as i32 - origin.x(), pos.y as i32 - origin.y())
+    }
+
+    pub fn rect_to_screen(&self, rect: Rect) -> Rect
+    {
+        let origin = self.origin();
+        Rect::new(rect.x() - origin.x(), rect.y() - origin.y(), rect.width(), rect.height())
+    }
+
+    pub fn screen_to_world(&self, pos: Point) -> PointF
+    {
+        let origin = self.origin();
+        PointF { x: (pos.x() + origin.x()) as f64, y: (pos.y() + origin.y()) as f64 }
+    }
+
+    fn clamped(&self, pos: PointF) -> PointF
+    {
+        PointF
+        {
+            x: pos.x.clamp(self.bounds.x() as f64, (self.bounds.right() as f64 - self.size.x).max(self.bounds.x() as f64)),
+            y: pos.y.clamp(self.bounds.y() as f64, (self.bounds.bottom() as f64 - self.size.y).max(self.bounds.y() as f64))
+        }
+    }
+}
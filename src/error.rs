@@ -0,0 +1,43 @@
+use std::fmt;
+
+use sdl2::messagebox::{self, MessageBoxFlag};
+
+#[derive(Debug)]
+pub enum Error
+{
+    Init(String), /* SDL or one of its libraries failed to start */
+    Video(String),
+    Asset { path: String, reason: String }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error
+{
+    pub fn asset(path: &str, reason: impl ToString) -> Error
+    {
+        Error::Asset { path: path.to_string(), reason: reason.to_string() }
+    }
+}
+
+impl fmt::Display for Error
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        match self
+        {
+            Error::Init(reason) => write!(f, "Failed to start SDL: {}", reason),
+            Error::Video(reason) => write!(f, "Failed to set up graphics: {}", reason),
+            Error::Asset { path, reason } => write!(f, "Failed to load {}: {}", path, reason)
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+//Logs the error and shows it in a dialog, there's no console on Windows
+pub fn report(err: &Error)
+{
+    eprintln!("Error: {}", err);
+    let _ = messagebox::show_simple_message_box(MessageBoxFlag::ERROR, "Game", &err.to_string(), None);
+}
@@ -1,74 +1,102 @@
 use rand::{rngs::ThreadRng, Rng};
-use sdl2::{rect::{Rect, Point}, mixer::Channel, keyboard::Keycode, render::{BlendMode, TextureCreator}, pixels::Color, video::WindowContext};
+use sdl2::{rect::Rect, keyboard::Keycode, render::TextureCreator, pixels::Color, video::WindowContext};
+
+use crate::assets::Assets;
+use crate::captions::Captions;
+use crate::difficulty::Difficulty;
+use crate::engine::{AnimatedSprite, Attenuation, Bus, Camera, PointF, Priority, Sprite, Video, Audio, EventLoop, Voice};
+use crate::error::Result;
+use crate::lighting::{self, Flicker, Light, Lighting};
+use crate::map::{self, EMPTY, FENCE, WARDROBE};
+use crate::noise::{self, Noise};
+use crate::particles::{self, Particles};
+use crate::settings::{Settings, SETTINGS_PATH};
+use crate::soundtrack::Soundtrack;
+use crate::tips::{Tip, Tips};
+
+//Random tiles tried when placing something before it's left out, a crowded map shouldn't hang
+const SEARCH_ATTEMPTS: u32 = 1000;
 
-use crate::engine::{PointF, Sprite, Video, Audio, EventLoop};
-
-pub struct Particle
+pub struct Candle
 {
-    pub pos: PointF,
-    pub lifetime: u8,
-    pub color: Color,
-    pub light: bool
+    pub dst: Rect,
+    pub lit: bool,
+    pub burn: f64 /* Wick left, gutters out at 0 */
 }
 
-pub struct Candle
+pub struct MatchBox
 {
     pub dst: Rect,
-    pub lit: bool
+    pub matches: u32
+}
+
+pub struct Player<'a>
+{
+    pub pos: PointF,
+    pub spr: AnimatedSprite<'a>,
+    pub facing: PointF, /* Last direction walked in, for the camera to look ahead */
+    pub matches: u32,
+    pub fear: f64, /* Panics at 1.0 */
+    pub stamina: f64,
+    pub exhausted: bool, /* Can't sprint until stamina recovers */
+    pub sprinting: bool,
+    pub hidden: bool,
+    pub breath: f64, /* Forced out of hiding at 0 */
+    pub bumping: bool
 }
 
 pub struct Ghost<'a>
 {
     pub pos: PointF,
     pub spr: Sprite<'a>,
-    pub spd: f64
+    pub spd: f64,
+    pub target: PointF, /* Last heard noise or a random spot to wander to */
+    pub investigating: bool,
+    pub saw_hide: bool
 }
 
-pub fn game(video: &mut Video, audio: &Audio, event: &mut EventLoop, texture_creator: &TextureCreator<WindowContext>, state: &mut i32)
+pub fn game<'a>(video: &mut Video, audio: &mut Audio, event: &mut EventLoop, texture_creator: &'a TextureCreator<WindowContext>, assets: &Assets<'a>, state: &mut i32, settings: &mut Settings) -> Result<()>
 {
+    let difficulty = &settings.difficulty();
     let mut rand = rand::thread_rng();
-    let ch0 = Channel(0);
-    ch0.set_volume(32);
-
-    let ch1 = Channel(1);
-    let ch2 = Channel(2);
 
     //Map & Player section
     let mut map = [(); 4096].map(|_| 0);
+    let mut explored = [false; 4096];
     let mut candles = Vec::<Candle>::new();
-    let mut player = Sprite::from_file(&texture_creator, "assets/sprites/player.png");
-    let mut player_pos = PointF { x: 0.0, y: 0.0 }; 
-    let mut player_anim: f64 = 0.0;
-    let mut particles = Vec::<Particle>::new(); 
+    let mut match_boxes = Vec::<MatchBox>::new();
+    let mut player = Player { pos: PointF { x: 0.0, y: 0.0 }, spr: assets.animated_sprite("player", "idle"), facing: PointF { x: 0.0, y: 0.0 }, matches: difficulty.matches, fear: 0.0, stamina: 1.0, exhausted: false, sprinting: false, hidden: false, breath: 1.0, bumping: false };
+    let mut particles = Particles::new(512);
+    let mut noises = Vec::<Noise>::new();
 
     //Enemy
-    let mut ghost = Ghost {spd: 1.0, pos: PointF { x: 0.0, y: 0.0 }, spr: Sprite::from_file(&texture_creator, "assets/sprites/ghost.png") };
+    let mut ghost = Ghost {spd: difficulty.ghost_speed, pos: PointF { x: 0.0, y: 0.0 }, spr: assets.sprite("ghost"), target: PointF { x: 0.0, y: 0.0 }, investigating: false, saw_hide: false };
     ghost.spr.alpha = 0;
 
-    gen_map(&mut player_pos, &mut map, &mut candles, &mut ghost, &mut rand);
+    gen_map(&mut player.pos, &mut map, &mut candles, &mut match_boxes, &mut ghost, &mut rand, difficulty);
 
-    player.src.set_width(16);
-    player.src.set_height(10);
-    player.dst.set_width(16);
-    player.dst.set_height(10);
+    let mut camera = Camera::new(64, 64, Rect::new(0, 0, 64 * 16, 64 * 16));
+    camera.follow(PointF { x: player.pos.x + 8.0, y: player.pos.y + 5.0 }, player.facing);
+    camera.snap();
 
     //Lights
-    let mut light_texture = texture_creator.create_texture_target(None, 64, 64).unwrap();
-    light_texture.set_blend_mode(BlendMode::Mod);
+    let mut lighting = Lighting::new(texture_creator, assets)?;
+    let mut lights = Vec::<Light>::new();
+    let moonlight = gen_moonlight(&map, &mut rand);
+    lighting.ambient = lighting::AMBIENT_PALETTE[rand.gen_range(0..lighting::AMBIENT_PALETTE.len())];
 
-    //Load assets
-    let fireamb = audio.load_music("assets/sounds/fireambient.wav");
-    let ambient = audio.load_sound("assets/sounds/ambient2.ogg");
-    let matches = audio.load_sound("assets/sounds/match.wav");
-    let ghost_snd = audio.load_sound("assets/sounds/ghost.ogg");
+    //Assets
+    let matches = assets.sound("match");
+    let ghost_snd = assets.sound("ghost");
+    let extinguish = assets.sound("extinguish");
 
-    fireamb.play(-1).unwrap();
-    ch0.play(&ambient, -1).unwrap();
+    let mut soundtrack = Soundtrack::start(audio, assets);
+    let mut tips = Tips::new(assets);
+    let mut captions = Captions::new(assets);
+    let mut ghost_voice = Voice::NONE;
 
-    let mut tilemap = Sprite::from_file(&texture_creator, "assets/sprites/objects.png");
-    let mut light = Sprite::from_file(&texture_creator, "assets/sprites/light.png");
-    let mut pixel = Sprite::from_file(&texture_creator, "assets/sprites/pixel.png");
-    light.set_blend_mod(BlendMode::Add);    
+    let mut tilemap = assets.sprite("objects");
+    let mut pixel = assets.sprite("pixel");
 
     let mut timer: f64 = 0.0;
     let mut match_timer: f64 = 0.0;
@@ -78,29 +106,57 @@ pub fn game(video: &mut Video, audio: &Audio, event: &mut EventLoop, texture_cre
         if !event.loop_start() { *state = -1; break; }
         video.canvas_mut().clear();
         
-        let mut c_x = 0;
-        let mut c_y = 0;
-        update_player(&event, video, &mut player, &mut player_anim, &mut player_pos, &mut c_x, &mut c_y);
+        noises.clear();
+        update_hiding(event, &mut player, &mut ghost, &map, &mut noises, difficulty);
+        if update_player(event, &mut player, &map, &mut noises, difficulty)
+        {
+            footstep(audio, assets, &player, &map, &mut noises, &mut rand, difficulty);
+        }
+        update_fear(&mut player, &ghost, &candles, &map, difficulty);
+
+        if player.fear > 0.25
+        {
+            tips.trigger(Tip::Dark, settings);
+        }
+
+        let center = PointF { x: player.pos.x + 8.0, y: player.pos.y + 5.0 };
+        map::reveal(&map, &mut explored, &center, difficulty.light_radius);
+
+        //Fear and the ghost closing in shake the screen
+        if player.fear > 0.5
+        {
+            camera.trauma = camera.trauma.max((player.fear - 0.5) * 0.8);
+        }
+
+        let ghost_dist = center.distance(&PointF { x: ghost.pos.x + 4.0, y: ghost.pos.y + 4.0 });
+        if ghost_dist < 32.0
+        {
+            camera.trauma = camera.trauma.max((1.0 - ghost_dist / 32.0) * 0.6);
+        }
+
+        camera.follow(center, player.facing);
+        camera.update();
+
+        soundtrack.update(audio, assets, threat(&player, &ghost, &candles), &mut rand);
 
         //Spawn particles
-        if timer as i32 % 2 == 0
+        if !player.hidden
         {
             let mut offset = 4.0;
     
-            if player.hflip 
+            if player.spr.sprite.hflip 
             { 
                 offset = 11.0; 
             }
-        
-            let mut color = Color::GRAY;
-            let mut light = false;
-            if timer as i32 % rand.gen_range(20..30) == 0
-            {
-                color = Color::YELLOW;
-                light = true;
-            }
 
-            spawn_particles(&mut particles, &mut rand, light, player_pos.x + offset, player_pos.y + 2.0, color);
+            let flame = PointF { x: player.pos.x + offset, y: player.pos.y + 4.0 };
+            particles.emit(&particles::SMOKE, flame, 1.0, &mut rand);
+            particles.emit(&particles::SPARKS, flame, 1.0, &mut rand);
+        }
+
+        for pos in &moonlight
+        {
+            particles.emit(&particles::DUST, *pos, 1.0, &mut rand);
         }
                 
 
@@ -111,25 +167,46 @@ pub fn game(video: &mut Video, audio: &Audio, event: &mut EventLoop, texture_cre
         tilemap.dst.set_height(16);
         
         //Draw map
-        let c_xx = (c_x / 16) as usize;
-        let c_yy = (c_y / 16) as usize;
+        let origin = camera.origin();
+        let c_xx = origin.x().div_euclid(16);
+        let c_yy = origin.y().div_euclid(16);
         for i in 0..5
         {
             for j in 0..5
             {
                 let x = i + c_xx;
                 let y = j + c_yy;
-                let v = map[x + y * 64];
-                if v == 0 { continue; }
+                if x < 0 || y < 0 || x >= 64 || y >= 64 { continue; }
+
+                let v = map[(x + y * 64) as usize];
+                if v == EMPTY { continue; }
                 
-                tilemap.dst.set_x((x * 16) as i32 - c_x);
-                tilemap.dst.set_y((y * 16) as i32 - c_y);
-                tilemap.src.set_x((v as i32 - 1) * 16);
+                tilemap.dst = camera.rect_to_screen(Rect::new(x * 16, y * 16, 16, 16));
+                tilemap.src.set_x(map::tile_src_x(v));
                 tilemap.draw(video.canvas_mut());                       
             }
         }
 
-        let player_rect = Rect::new(player_pos.x as i32 + 5, player_pos.y as i32 + 5, 6, 5);
+        let player_rect = Rect::new(player.pos.x as i32 + 5, player.pos.y as i32 + 5, 6, 5);
+
+        //Pick up match boxes
+        match_boxes.retain(|match_box|
+        {
+            if match_box.dst.has_intersection(player_rect)
+            {
+                player.matches += match_box.matches;
+                return false;
+            }
+
+            true
+        });
+
+        for match_box in &match_boxes
+        {
+            tilemap.src = Rect::new(40, 0, 5, 3);
+            tilemap.dst = camera.rect_to_screen(match_box.dst);
+            tilemap.draw(video.canvas_mut());
+        }
 
         //Update and Draw candles
         for i in 0..candles.len()
@@ -138,18 +215,25 @@ pub fn game(video: &mut Video, audio: &Audio, event: &mut EventLoop, texture_cre
 
             //Setup tilemap
             tilemap.src = Rect::new(32, 0, 6, 4);
-            tilemap.dst = Rect::new(candle.dst.x() - c_x, candle.dst.y() - c_y, 6, 4);
+            tilemap.dst = camera.rect_to_screen(Rect::new(candle.dst.x(), candle.dst.y(), 6, 4));
             tilemap.draw(video.canvas_mut());
 
-            if candle.dst.has_intersection(player_rect) && !candle.lit && event.is_key_pressed(Keycode::Z)
+            if candle.dst.has_intersection(player_rect) && !candle.lit && player.matches > 0
+            {
+                tips.trigger(Tip::Candle, settings);
+            }
+
+            if candle.dst.has_intersection(player_rect) && !candle.lit && !player.hidden && player.matches > 0 && event.is_key_pressed(Keycode::Z)
             {
-                match_timer += 0.01;
+                if match_timer == 0.0
+                {
+                    noise::emit(&mut noises, candle_pos(candle), noise::MATCH_STRIKE);
+                }
+
+                match_timer += difficulty.match_speed;
 
                 //Draw progress bar
-                pixel.dst.set_x(candle.dst.x() - c_x);
-                pixel.dst.set_y(candle.dst.y() - c_y - 8);
-                pixel.dst.set_width(candle.dst.width() - 2);
-                pixel.dst.set_height(1);
+                pixel.dst = camera.rect_to_screen(Rect::new(candle.dst.x(), candle.dst.y() - 8, candle.dst.width() - 2, 1));
                 pixel.set_color_mod(Color::RED);
                 pixel.draw(video.canvas_mut());
 
@@ -160,165 +244,183 @@ pub fn game(video: &mut Video, audio: &Audio, event: &mut EventLoop, texture_cre
 
                 if match_timer > 1.0
                 {
-                    if ghost.spd < 4.0
+                    //Only a fresh candle makes the ghost angrier, relighting doesn't
+                    if candle.burn > 0.0 && ghost.spd < difficulty.ghost_speed_max
                     {
-                        ghost.spd += 0.5;
+                        ghost.spd += difficulty.ghost_speed_step;
                     }
 
                     match_timer = 0.0; 
+                    player.matches -= 1;
                     candle.lit = true;
-                    ch2.play(&matches, 0).unwrap();
+                    candle.burn = 1.0;
+                    let voice = audio.play(matches, Bus::Sfx, Priority::Normal, 0);
+                    place_sound(audio, voice, &map, &center, &candle_pos(candle), 96.0);
+                    noise::emit(&mut noises, candle_pos(candle), noise::CANDLE_LIT);
                 }
             } 
             else if !event.is_key_pressed(Keycode::Z)
             {
+                //Match burnt for too long before letting go is wasted
+                if match_timer > difficulty.match_waste
+                {
+                    player.matches -= 1;
+                }
+
                 match_timer = 0.0;  
             }
 
-            if candle.lit && timer as i32 % 4 == 0
+            //Burn down
+            if candle.lit
             {
-                let mut color = Color::GRAY;
-                let mut light = false;
-                
-                if timer as i32 % rand.gen_range(20..30) == 0
+                candle.burn -= difficulty.candle_burn;
+
+                if candle.burn <= 0.0
                 {
-                    color = Color::YELLOW;
-                    light = true;
+                    candle.burn = 0.0;
+                    candle.lit = false;
+                    let voice = audio.play(extinguish, Bus::Sfx, Priority::Normal, 0);
+                    place_sound(audio, voice, &map, &center, &candle_pos(candle), 128.0);
+
+                    if candle_pos(candle).distance(&center) < 48.0
+                    {
+                        camera.add_trauma(0.3);
+                    }
                 }
+            }
 
-                spawn_particles(&mut particles, &mut rand, light, candle.dst.x() as f64 + 2.0, candle.dst.y() as f64, color);
+            //Smoke thins out as the wick burns down
+            if candle.lit
+            {
+                let flame = PointF { x: candle.dst.x() as f64 + 2.0, y: candle.dst.y() as f64 + 2.0 };
+                let scale = 0.5 * (0.3 + 0.7 * candle.burn);
+                particles.emit(&particles::SMOKE, flame, scale, &mut rand);
+                particles.emit(&particles::SPARKS, flame, scale, &mut rand);
             }
         }
 
-        if player_rect.has_intersection(Rect::new(ghost.pos.x as i32, ghost.pos.y as i32, 8, 8))
+        //Out of matches with candles left to light
+        let out_of_matches = player.matches == 0 && match_boxes.is_empty() && candles.iter().any(|candle| !candle.lit);
+
+        //The ghost walks right past a hiding player unless it saw them go in
+        let caught = player_rect.has_intersection(Rect::new(ghost.pos.x as i32, ghost.pos.y as i32, 8, 8)) && (!player.hidden || ghost.saw_hide);
+
+        if out_of_matches || player.fear >= 1.0 || caught
         {
-            sdl2::mixer::Music::halt();
             *state = 2;
             break;
         }
         
         //Particles
-        for i in 0..particles.len()
+        particles.update();
+        for part in particles.iter()
         {
-            let part = match particles.get_mut(i)
-            {
-                Some(part) => part,
-                None => continue,
-            };
-
-            part.lifetime += 1;
-            part.pos.y -= 0.7;
-            
-            if part.lifetime >= 255
-            {
-                particles.remove(i);
-                continue;
-            }
-
-            if part.pos.x <= (c_x).into() || part.pos.y <= (c_y).into() || part.pos.x > (c_x + 64).into() || part.pos.y > (c_y + 64).into()
+            let screen = camera.world_to_screen(&part.pos);
+            if screen.x() < 0 || screen.y() < 0 || screen.x() >= 64 || screen.y() >= 64
             {
                 continue;
             }
             
-            pixel.dst.set_width(1);
-            pixel.dst.set_height(1);
-            pixel.set_color_mod(part.color);
-            pixel.dst.set_x(part.pos.x as i32 - c_x);
-            pixel.dst.set_y(part.pos.y as i32 - c_y);
+            pixel.dst = Rect::new(screen.x(), screen.y(), 1, 1);
+            pixel.set_color_mod(part.color());
             pixel.draw(video.canvas_mut());
         }
 
         //Draw light
-        video.canvas_mut().with_texture_canvas(&mut light_texture, |canvas|
         {
-            canvas.clear();
+            lights.clear();
 
             //Player light
-            let size = (45.0 + timer.sin() * 3.0) as i32;
-            light.dst.set_x(player_pos.x as i32 + 8 - c_x - (size / 2));
-            light.dst.set_y(player_pos.y as i32 + 5 - c_y - (size / 2));
-            light.dst.set_width(size as u32);
-            light.dst.set_height(size as u32);
-            light.draw(canvas);
-
-            //Particle light
-            for i in 0..particles.len()
+            let mut radius = difficulty.light_radius;
+            let mut amplitude = (3.0 + player.fear * 6.0 + if player.sprinting { 4.0 } else { 0.0 }) / 2.0;
+            if player.hidden
             {
-                let part = particles.get_mut(i).unwrap();
-                if !part.light { continue; }
+                radius /= 3.0;
+                amplitude /= 3.0;
+            }
 
-                if part.pos.x <= (c_x).into() || part.pos.y <= (c_y).into() || part.pos.x > (c_x + 64).into() || part.pos.y > (c_y + 64).into()
-                {
-                    continue;
-                }
+            lights.push(Light
+            {
+                color: lighting::HAND_CANDLE,
+                flicker: Flicker::Sine { amplitude, speed: 1.0 },
+                ..Light::new(PointF { x: player.pos.x + 8.0, y: player.pos.y + 5.0 }, radius)
+            });
 
-                let size = 6;
-                light.dst.set_x(part.pos.x as i32 - c_x - (size / 2));
-                light.dst.set_y(part.pos.y as i32 - c_y - (size / 2));
-                light.dst.set_width(size as u32);
-                light.dst.set_height(size as u32);
-                light.draw(canvas);
+            //Particle light, tinted like the particle
+            for part in particles.iter().filter(|part| part.emitter.light > 0.0)
+            {
+                lights.push(Light { color: part.color(), ..Light::new(part.pos, part.emitter.light) });
             }
 
-            //Candle light
-            let size = (16.0 + timer.sin() * 3.0) as i32;
-            light.dst.set_width(size as u32);
-            light.dst.set_height(size as u32);
+            //Ghost glows while it's visible
+            if ghost.spr.alpha > 0
+            {
+                lights.push(Light
+                {
+                    color: lighting::GHOST,
+                    intensity: ghost.spr.alpha as f64 / 255.0,
+                    ..Light::new(PointF { x: ghost.pos.x + 4.0, y: ghost.pos.y + 4.0 }, 10.0)
+                });
+            }
 
-            for i in 0..candles.len()
+            //Moonlight
+            for pos in &moonlight
             {
-                let candle = candles.get_mut(i).unwrap();
-                if !candle.lit { continue; }
+                lights.push(Light { color: lighting::MOONLIGHT, intensity: 0.6, ..Light::new(*pos, 12.0) });
+            }
 
-                light.dst.set_x(candle.dst.x() + 3 - c_x - (size / 2));
-                light.dst.set_y(candle.dst.y() + 2 - c_y - (size / 2));
-                light.draw(canvas);
+            //Candle light
+            for candle in candles.iter().filter(|candle| candle.lit)
+            {
+                lights.push(Light
+                {
+                    color: lighting::CANDLE,
+                    flicker: Flicker::Sine { amplitude: 1.5 * candle.burn, speed: 1.0 },
+                    ..Light::new(candle_pos(candle), 3.0 + 5.0 * candle.burn)
+                });
             }
 
-        }).unwrap();
+            //Losing the render targets costs a frame, not the game
+            if let Err(err) = lighting.render(video.canvas_mut(), &map, &lights, &camera, timer)
+            {
+                eprintln!("Warning: {}, skipping a frame", err);
+                event.loop_end();
+                continue;
+            }
+        }
 
         //Update and draw ghost
         {
-            update_ghost(&mut ghost, &player_pos);
-            
-            if ghost.pos.x as i32 % 8 == 0 || ghost.pos.y as i32 % 8 == 0
+            update_ghost(&mut ghost, &player, &map, &noises, &mut rand, difficulty);
+
+            let ghost_center = PointF { x: ghost.pos.x + 4.0, y: ghost.pos.y + 4.0 };
+            if ghost.spr.alpha > 0 && center.distance(&ghost_center) < difficulty.light_radius && map::line_of_sight(&map, &center, &ghost_center).visible
             {
-                spawn_particles(&mut particles, &mut rand, true, ghost.pos.x + 2.0, ghost.pos.y + 1.0, Color::RED);
+                tips.trigger(Tip::Ghost, settings);
             }
+            
+            particles.emit(&particles::GHOST_TRAIL, PointF { x: ghost.pos.x + 2.0, y: ghost.pos.y + 3.0 }, 1.0, &mut rand);
 
             if (ghost.pos.x as i32 % 8 == 0 || ghost.pos.y as i32 % 8 == 0) && ghost.spr.alpha == 0
             {
-                ch1.play(&ghost_snd, 0).unwrap();
+                ghost_voice = audio.play(ghost_snd, Bus::Voice, Priority::High, 0);
                 ghost.spr.alpha = 255;
             }
 
-            //"3D" sound
-            {
-                let xx = ghost.pos.x - player_pos.x;
-                let a_x = xx.clamp(-32.0, 32.0) / 32.0;
-                let yy = ghost.pos.y - player_pos.y;
-                
-                let left = (a_x * 255.0 - 255.0).abs().clamp(0.0, 255.0) as u8;
-                let right = (a_x * 255.0) as u8;
-                ch1.set_panning(left, right).unwrap();
-
-                let vol = 128 - (xx + yy).abs().clamp(0.0, 128.0) as i32;
-                ch1.set_volume(vol);
-            }
+            //Follow the ghost while its moan plays
+            place_sound(audio, ghost_voice, &map, &center, &ghost_center, 128.0);
 
-            ghost.spr.dst.set_x(ghost.pos.x as i32 / 8 * 8);
-            ghost.spr.dst.set_y(ghost.pos.y as i32 / 8 * 8);
-            ghost.spr.draw_offset(video.canvas_mut(), Point::new(-c_x, -c_y));
+            ghost.spr.dst = camera.rect_to_screen(Rect::new(ghost.pos.x as i32 / 8 * 8, ghost.pos.y as i32 / 8 * 8, ghost.spr.dst.width(), ghost.spr.dst.height()));
+            ghost.spr.draw(video.canvas_mut());
         }
 
         //Draw player
+        if !player.hidden
         {
             let mut offset = 4;
-            if player.hflip { offset = -4; }
-            player.src.set_x((player_anim as i32) * 16);
-            player.dst.set_x(player_pos.x as i32 + offset);
-            player.dst.set_y(player_pos.y as i32);
-            player.draw_offset(video.canvas_mut(), Point::new(-c_x, -c_y));
+            if player.spr.sprite.hflip { offset = -4; }
+            player.spr.sprite.dst = camera.rect_to_screen(Rect::new(player.pos.x as i32 + offset, player.pos.y as i32, 16, 10));
+            player.spr.draw(video.canvas_mut());
         }
 
         timer += 0.5;
@@ -336,13 +438,9 @@ pub fn game(video: &mut Video, audio: &Audio, event: &mut EventLoop, texture_cre
             {
                 for j in 0..64
                 {
-                    let tile = map[i + j * 64];
-                    let color = match tile
-                    {
-                        1 => Color::RGB(0, 94, 41),
-                        2 => Color::RGB(94, 25, 0),
-                        _ => Color::BLACK
-                    };
+                    if !explored[i + j * 64] { continue; }
+
+                    let color = map::tile_color(map[i + j * 64]);
 
                     pixel.dst.set_width(1);
                     pixel.dst.set_height(1);
@@ -357,7 +455,7 @@ pub fn game(video: &mut Video, audio: &Audio, event: &mut EventLoop, texture_cre
             for i in 0..candles.len()
             {
                 let candle = candles.get(i).unwrap();
-                if candle.lit { continue; }
+                if candle.lit || !explored[(candle.dst.x() / 16 + candle.dst.y() / 16 * 64) as usize] { continue; }
 
                 pixel.dst.set_x(candle.dst.x() / 16);
                 pixel.dst.set_y(candle.dst.y() / 16);
@@ -366,8 +464,8 @@ pub fn game(video: &mut Video, audio: &Audio, event: &mut EventLoop, texture_cre
                 pixel.draw(video.canvas_mut());
             }
 
-            pixel.dst.set_x((player_pos.x as i32 + 5) / 16);
-            pixel.dst.set_y((player_pos.y as i32 + 5) / 16);
+            pixel.dst.set_x((player.pos.x as i32 + 5) / 16);
+            pixel.dst.set_y((player.pos.y as i32 + 5) / 16);
 
             pixel.set_color_mod(Color::RGB(230, 11, 0));
             pixel.draw(video.canvas_mut());
@@ -378,14 +476,75 @@ pub fn game(video: &mut Video, audio: &Audio, event: &mut EventLoop, texture_cre
         }
 
         //Draw light texture
-        video.canvas_mut().copy(&light_texture, None, None).unwrap();
+        if let Err(err) = lighting.draw(video.canvas_mut())
+        {
+            eprintln!("Warning: {}, skipping a frame", err);
+            event.loop_end();
+            continue;
+        }
+
+        tips.update(audio, assets);
+        tips.draw(video.canvas_mut());
+
+        audio.update_captions();
+        if settings.captions
+        {
+            captions.draw(video.canvas_mut(), audio);
+        }
+
+        //Matches left
+        pixel.dst.set_width(1);
+        for i in 0..player.matches.min(20) as i32
+        {
+            pixel.dst.set_x(1 + i * 2);
+            pixel.dst.set_y(1);
+            pixel.dst.set_height(1);
+            pixel.set_color_mod(Color::RED);
+            pixel.draw(video.canvas_mut());
+
+            pixel.dst.set_y(2);
+            pixel.dst.set_height(2);
+            pixel.set_color_mod(Color::RGB(222, 190, 120));
+            pixel.draw(video.canvas_mut());
+        }
+
+        //Stamina
+        if player.stamina < 1.0
+        {
+            pixel.dst.set_x(1);
+            pixel.dst.set_y(62);
+            pixel.dst.set_width((player.stamina * 20.0) as u32);
+            pixel.dst.set_height(1);
+            pixel.set_color_mod(if player.exhausted { Color::RED } else { Color::WHITE });
+            pixel.draw(video.canvas_mut());
+        }
+
+        //Held breath
+        if player.breath < 1.0
+        {
+            pixel.dst.set_x(1);
+            pixel.dst.set_y(60);
+            pixel.dst.set_width((player.breath * 20.0) as u32);
+            pixel.dst.set_height(1);
+            pixel.set_color_mod(Color::CYAN);
+            pixel.draw(video.canvas_mut());
+        }
         
         video.canvas_mut().present();
         event.loop_end();
     }
+
+    //The loops would otherwise keep their channels into the next round
+    soundtrack.fade_out(audio, 800);
+    audio.clear_captions();
+
+    //Remember the tips seen this round
+    settings.save(SETTINGS_PATH);
+
+    Ok(())
 }
 
-pub fn gen_map(player: &mut PointF, map: &mut [u8; 4096], candles: &mut Vec<Candle>, ghost: &mut Ghost,  rand: &mut ThreadRng)
+pub fn gen_map(player: &mut PointF, map: &mut [u8; 4096], candles: &mut Vec<Candle>, match_boxes: &mut Vec<MatchBox>, ghost: &mut Ghost,  rand: &mut ThreadRng, difficulty: &Difficulty)
 {
     for i in 0..64
     {
@@ -396,15 +555,53 @@ pub fn gen_map(player: &mut PointF, map: &mut [u8; 4096], candles: &mut Vec<Cand
         }
     }
 
+    //Fences
+    for _i in 0..24
+    {
+        let (mut x, mut y) = (rand.gen_range(0..64), rand.gen_range(0..64));
+        let (dx, dy) = if rand.gen_bool(0.5) { (1, 0) } else { (0, 1) };
+
+        for _j in 0..rand.gen_range(3..8)
+        {
+            if x >= 64 || y >= 64 { break; }
+
+            map[x + y * 64] = FENCE;
+            x += dx;
+            y += dy;
+        }
+    }
+
     player.x = (rand.gen_range(0..64) * 16) as f64;
     player.y = (rand.gen_range(0..64) * 16) as f64;
 
+    //Don't start inside a fence
+    map[(player.x / 16.0) as usize + (player.y / 16.0) as usize * 64] = EMPTY;
+
     ghost.pos.x = player.x + (rand.gen_range(0..360) as f64).sin() * (rand.gen_range(4..8) * 16) as f64;
     ghost.pos.y = player.y + (rand.gen_range(0..360) as f64).sin() * (rand.gen_range(4..8) * 16) as f64;
 
-    for _i in 0..(rand.gen_range(8..12) as usize)
+    //Starts off wandering from where it is
+    ghost.target = ghost.pos;
+
+    for _i in 0..difficulty.hiding_spots
     {
-        'search: loop 
+        'search: for _attempt in 0..SEARCH_ATTEMPTS
+        {
+            let r_x = rand.gen_range(0..64);
+            let r_y = rand.gen_range(0..64);
+            let tile = map[r_x + r_y * 64];
+
+            if tile == EMPTY
+            {
+                map[r_x + r_y * 64] = WARDROBE;
+                break 'search;
+            } 
+        }
+    }
+
+    for _i in 0..rand.gen_range(difficulty.candles_min..difficulty.candles_max)
+    {
+        'search: for _attempt in 0..SEARCH_ATTEMPTS
         {
             let r_x = rand.gen_range(0..64);
             let r_y = rand.gen_range(0..64);
@@ -412,86 +609,298 @@ pub fn gen_map(player: &mut PointF, map: &mut [u8; 4096], candles: &mut Vec<Cand
 
             if tile == 0
             {
-                let candle = Candle { dst: Rect::new(r_x as i32 * 16, r_y as i32 * 16, 8, 8), lit: false };
+                let candle = Candle { dst: Rect::new(r_x as i32 * 16, r_y as i32 * 16, 8, 8), lit: false, burn: 1.0 };
                 candles.push(candle);
                 break 'search;
             } 
         }
     }
+
+    for _i in 0..difficulty.match_boxes
+    {
+        'search: for _attempt in 0..SEARCH_ATTEMPTS
+        {
+            let r_x = rand.gen_range(0..64);
+            let r_y = rand.gen_range(0..64);
+            let tile = map[r_x + r_y * 64];
+
+            if tile == 0
+            {
+                let match_box = MatchBox { dst: Rect::new(r_x as i32 * 16 + 8, r_y as i32 * 16 + 8, 5, 3), matches: difficulty.matches_per_box };
+                match_boxes.push(match_box);
+                break 'search;
+            } 
+        }
+    }
 }
 
-pub fn update_player(event: &EventLoop, video: &mut Video, player: &mut Sprite, player_anim: &mut f64, player_pos: &mut PointF, c_x: &mut i32, c_y: &mut i32)
+//Gaps in the canopy the moon shines through
+fn gen_moonlight(map: &[u8; 4096], rand: &mut ThreadRng) -> Vec<PointF>
 {
+    let mut moonlight = Vec::new();
+
+    for _i in 0..rand.gen_range(6..10)
+    {
+        'search: for _attempt in 0..SEARCH_ATTEMPTS
+        {
+            let r_x = rand.gen_range(0..64);
+            let r_y = rand.gen_range(0..64);
+
+            if map[r_x + r_y * 64] == EMPTY
+            {
+                moonlight.push(PointF { x: r_x as f64 * 16.0 + 8.0, y: r_y as f64 * 16.0 + 8.0 });
+                break 'search;
+            } 
+        }
+    }
+
+    moonlight
+}
+
+//Returns whether a foot landed this frame
+pub fn update_player(event: &EventLoop, player: &mut Player, map: &[u8; 4096], noises: &mut Vec<Noise>, difficulty: &Difficulty) -> bool
+{
+    let last_pos = player.pos;
+
+    //Sprint
+    player.sprinting = event.is_key_pressed(Keycode::X) && !player.exhausted;
+
+    let mut speed = 0.5;
+    if player.sprinting
+    {
+        speed *= difficulty.sprint_speed;
+    }
+    else if player.exhausted
+    {
+        speed *= difficulty.exhausted_speed;
+    }
+
     //Control
     let mut walking = false;
+    let mut facing = PointF { x: 0.0, y: 0.0 };
 
-    if event.is_key_pressed(Keycode::Left)
+    if player.hidden
+    {
+        //Can't move while hiding
+    }
+    else if event.is_key_pressed(Keycode::Left)
     {
-        player_pos.x -= 0.5;
-        player.hflip = false;
+        player.pos.x -= speed;
+        player.spr.sprite.hflip = false;
+        facing.x = -1.0;
         walking = true;
     }
     else if event.is_key_pressed(Keycode::Right)
     {
-        player_pos.x += 0.5;
-        player.hflip = true;
+        player.pos.x += speed;
+        player.spr.sprite.hflip = true;
+        facing.x = 1.0;
         walking = true;
     }
 
-    if event.is_key_pressed(Keycode::Up)
+    if player.hidden
     {
-        player_pos.y -= 0.5;
+        //Can't move while hiding
+    }
+    else if event.is_key_pressed(Keycode::Up)
+    {
+        player.pos.y -= speed;
+        facing.y = -1.0;
         walking = true;
     }
     else if event.is_key_pressed(Keycode::Down)
     {
-        player_pos.y += 0.5 ;
+        player.pos.y += speed;
+        facing.y = 1.0;
         walking = true;
     }
 
-    //Animate player
     if walking
     {
-        if *player_anim < 4.0 {
-            *player_anim = 4.0;
-        }
+        player.facing = facing;
+    }
 
-        *player_anim += 0.2;
+    //Stamina
+    player.sprinting &= walking;
+    if player.sprinting
+    {
+        player.stamina -= difficulty.stamina_drain;
 
-        if *player_anim > 7.0 {
-            *player_anim = 4.0;
+        if player.stamina <= 0.0
+        {
+            player.stamina = 0.0;
+            player.exhausted = true;
         }
-
-    } 
-    else 
+    }
+    else
     {
-        if *player_anim >= 4.0 {
-            *player_anim = 0.0;
+        player.stamina = (player.stamina + difficulty.stamina_regen).min(1.0);
+
+        if player.stamina >= 0.5
+        {
+            player.exhausted = false;
         }
+    }
 
-        *player_anim += 0.2;
+    //Collide with fences one axis at a time so the player slides along them
+    let mut bumped = false;
+    if map::box_hits_solid(map, player.pos.x + 5.0, last_pos.y + 5.0, 6.0, 5.0)
+    {
+        player.pos.x = last_pos.x;
+        bumped = true;
+    }
 
-        if *player_anim > 3.0 {
-            *player_anim = 0.0;
-        }
+    if map::box_hits_solid(map, player.pos.x + 5.0, player.pos.y + 5.0, 6.0, 5.0)
+    {
+        player.pos.y = last_pos.y;
+        bumped = true;
     }
 
+    let center = PointF { x: player.pos.x + 8.0, y: player.pos.y + 5.0 };
+    if bumped && !player.bumping
+    {
+        noise::emit(noises, center, noise::BUMP);
+    }
+    player.bumping = bumped;
+
+    //Animate player
+    player.spr.play(if walking { "walk" } else { "idle" });
+
+    //Feet land on the walk cycle's step frames
+    let stepped = player.spr.update() == Some("step");
+
     //Clamp player's position
-    player_pos.x = player_pos.x.clamp(-5.0, 64.0 * 16.0 - 25.0);
-    player_pos.y = player_pos.y.clamp(-5.0, 64.0 * 16.0 - 25.0);
+    player.pos.x = player.pos.x.clamp(-5.0, 64.0 * 16.0 - 25.0);
+    player.pos.y = player.pos.y.clamp(-5.0, 64.0 * 16.0 - 25.0);
+
+    stepped
+}
+
+//Plays a footstep for the tile underfoot and lets the ghost hear it, louder when running
+fn footstep(audio: &mut Audio, assets: &Assets, player: &Player, map: &[u8; 4096], noises: &mut Vec<Noise>, rand: &mut ThreadRng, difficulty: &Difficulty)
+{
+    let feet = PointF { x: player.pos.x + 8.0, y: player.pos.y + 9.0 };
+    let surface = map::surface(map::tile_at(map, feet.x, feet.y));
+
+    //A random take and volume so steps don't sound identical
+    let voice = audio.play(assets.sound(&format!("step_{}_{}", surface.name(), rand.gen_range(1..=3))), Bus::Sfx, Priority::Low, 0);
+    let volume = if player.sprinting { 0.9 } else { 0.6 };
+    audio.set_volume(voice, volume * rand.gen_range(0.8..1.0));
+
+    let radius = if player.sprinting { difficulty.sprint_noise } else { difficulty.step_noise };
+    noise::emit(noises, PointF { x: player.pos.x + 8.0, y: player.pos.y + 5.0 }, radius * surface.loudness());
+}
+
+fn update_fear(player: &mut Player, ghost: &Ghost, candles: &[Candle], map: &[u8; 4096], difficulty: &Difficulty)
+{
+    let center = PointF { x: player.pos.x + 8.0, y: player.pos.y + 5.0 };
+
+    //Lit candles nearby calm the player down, darkness does the opposite
+    let calm = candles.iter().any(|candle| candle.lit && center.distance(&candle_pos(candle)) < 24.0 && map::line_of_sight(map, &candle_pos(candle), &center).visible);
+    if calm
+    {
+        player.fear -= difficulty.fear_calm;
+    }
+    else
+    {
+        player.fear += difficulty.fear_dark;
+    }
+
+    //The closer the ghost the scarier
+    let ghost_dist = center.distance(&PointF { x: ghost.pos.x + 4.0, y: ghost.pos.y + 4.0 });
+    if ghost_dist < 48.0
+    {
+        player.fear += difficulty.fear_ghost * (1.0 - ghost_dist / 48.0);
+    }
+
+    player.fear = player.fear.clamp(0.0, 1.0);
+}
+
+//How tense the soundtrack should be, from the ghost closing in, fear and how angry lit candles have made it
+fn threat(player: &Player, ghost: &Ghost, candles: &[Candle]) -> f64
+{
+    let center = PointF { x: player.pos.x + 8.0, y: player.pos.y + 5.0 };
+    let proximity = 1.0 - (center.distance(&PointF { x: ghost.pos.x + 4.0, y: ghost.pos.y + 4.0 }) / 96.0).min(1.0);
+    let lit = candles.iter().filter(|candle| candle.lit).count() as f64 / candles.len().max(1) as f64;
+
+    (0.5 * proximity + 0.3 * player.fear + 0.2 * lit).clamp(0.0, 1.0)
+}
+
+fn update_hiding(event: &EventLoop, player: &mut Player, ghost: &mut Ghost, map: &[u8; 4096], noises: &mut Vec<Noise>, difficulty: &Difficulty)
+{
+    let center = PointF { x: player.pos.x + 8.0, y: player.pos.y + 5.0 };
+
+    if player.hidden
+    {
+        player.breath -= difficulty.breath_drain;
+
+        //Step out, or gasp for air and get forced out
+        if event.is_key_down(Keycode::C) || player.breath <= 0.0
+        {
+            if player.breath <= 0.0
+            {
+                noise::emit(noises, center, noise::GASP);
+            }
+
+            player.breath = player.breath.max(0.0);
+            player.hidden = false;
+            ghost.saw_hide = false;
+        }
+    }
+    else
+    {
+        player.breath = (player.breath + difficulty.breath_regen).min(1.0);
+
+        if event.is_key_down(Keycode::C) && map::tile_at(map, center.x, center.y) == WARDROBE
+        {
+            player.hidden = true;
+            ghost.saw_hide = ghost_sees(ghost, &center, map, difficulty);
+        }
+    }
+}
 
-    //Set camera to new position
-    *c_x = (player_pos.x - 32.0 + 8.0).clamp(0.0, 944.0) as i32;
-    *c_y = (player_pos.y - 32.0 + 5.0).clamp(0.0, 944.0) as i32;
+fn ghost_sees(ghost: &Ghost, point: &PointF, map: &[u8; 4096], difficulty: &Difficulty) -> bool
+{
+    let eye = PointF { x: ghost.pos.x + 4.0, y: ghost.pos.y + 4.0 };
+    eye.distance(point) < difficulty.ghost_sight && map::line_of_sight(map, &eye, point).visible
 }
 
-fn update_ghost(ghost: &mut Ghost, player: &PointF)
+fn update_ghost(ghost: &mut Ghost, player: &Player, map: &[u8; 4096], noises: &[Noise], rand: &mut ThreadRng, difficulty: &Difficulty)
 {
-    let dir_x = ((player.x + 8.0) - ghost.pos.x).signum(); 
-    let dir_y = ((player.y + 5.0) - ghost.pos.y).signum(); 
+    let center = PointF { x: player.pos.x + 8.0, y: player.pos.y + 5.0 };
+
+    //Chase the player when close enough to see them, otherwise follow noises
+    if (!player.hidden || ghost.saw_hide) && ghost_sees(ghost, &center, map, difficulty)
+    {
+        ghost.target = center;
+        ghost.investigating = true;
+    }
+    else if let Some(noise) = noise::loudest(noises, &ghost.pos)
+    {
+        ghost.target = noise.pos;
+        ghost.investigating = true;
+    }
+
+    //Nothing there, wander around instead without leaving the map
+    if ghost.pos.distance(&ghost.target) < 2.0
+    {
+        let edge = map::MAP_SIZE as f64 * map::TILE_SIZE - 8.0;
+        ghost.target.x = (ghost.pos.x + rand.gen_range(-64.0..64.0)).clamp(0.0, edge);
+        ghost.target.y = (ghost.pos.y + rand.gen_range(-64.0..64.0)).clamp(0.0, edge);
+        ghost.investigating = false;
+    }
+
+    let dir_x = (ghost.target.x - ghost.pos.x).signum(); 
+    let dir_y = (ghost.target.y - ghost.pos.y).signum(); 
 
-    ghost.pos.x += dir_x * 0.1 * ghost.spd;
-    ghost.pos.y += dir_y * 0.1 * ghost.spd;
+    let mut spd = ghost.spd;
+    if !ghost.investigating
+    {
+        spd *= 0.5;
+    }
+
+    ghost.pos.x += dir_x * 0.1 * spd;
+    ghost.pos.y += dir_y * 0.1 * spd;
     
     if ghost.spr.alpha > 0
     {
@@ -499,15 +908,14 @@ fn update_ghost(ghost: &mut Ghost, player: &PointF)
     }
 }
 
-fn spawn_particles(particles: &mut Vec<Particle>, rand: &mut ThreadRng, emit_light: bool, x: f64, y: f64, color: Color)
-{    
-    let part = Particle 
-    { 
-        light: emit_light, /* Emit lights */ 
-        pos: PointF { x: x + rand.gen_range(-1..2) as f64, y: y + 2.0 }, 
-        lifetime: 200, 
-        color  
-    };
-
-    particles.push(part);
-}
\ No newline at end of file
+//Positions a sound relative to the player, walls in between muffle it
+fn place_sound(audio: &mut Audio, voice: Voice, map: &[u8; 4096], listener: &PointF, source: &PointF, range: f64)
+{
+    let occluded = !map::line_of_sight(map, listener, source).visible;
+    audio.set_spatial(voice, listener, source, range, Attenuation::Inverse, occluded);
+}
+
+fn candle_pos(candle: &Candle) -> PointF
+{
+    PointF { x: candle.dst.x() as f64 + 3.0, y: candle.dst.y() as f64 + 2.0 }
+}
@@ -1,14 +1,19 @@
-use sdl2::{mixer::Channel, render::{TextureCreator}, video::WindowContext};
-use crate::engine::{Sprite, Video, Audio, EventLoop};
+use sdl2::rect::Rect;
+use crate::assets::Assets;
+use crate::captions::Captions;
+use crate::engine::{Bus, Camera, Priority, Video, Audio, EventLoop};
+use crate::settings::Settings;
 
-pub fn jumpscare(video: &mut Video, audio: &Audio, event: &mut EventLoop, texture_creator: &TextureCreator<WindowContext>, state: &mut i32)
+pub fn jumpscare(video: &mut Video, audio: &mut Audio, event: &mut EventLoop, assets: &Assets, state: &mut i32, settings: &Settings)
 {
-    let jumpscare_snd = audio.load_sound("assets/sounds/jumpscare.wav");
-    Channel::all().play(&jumpscare_snd, 0).unwrap();
+    audio.play(assets.sound("jumpscare"), Bus::Sfx, Priority::High, 0);
+    let mut captions = Captions::new(assets);
 
-    let mut jumpscare_texture = Sprite::from_file(&texture_creator, "assets/sprites/jumpscare.png");
-    jumpscare_texture.dst.set_width(64);
-    jumpscare_texture.src.set_width(64);
+    let mut jumpscare_texture = assets.animated_sprite("jumpscare", "lunge");
+
+    //Shakes hard while the face lunges in, then settles
+    let mut camera = Camera::new(64, 64, Rect::new(0, 0, 64, 64));
+    camera.max_shake = 6.0;
 
     let mut cnt = 0.0;
     loop 
@@ -16,18 +21,34 @@ pub fn jumpscare(video: &mut Video, audio: &Audio, event: &mut EventLoop, textur
         if !event.loop_start() { *state = -1; break; }
         video.canvas_mut().clear();
 
-        jumpscare_texture.src.set_x((cnt as i32).clamp(0, 4) * 64);
+        jumpscare_texture.update();
+        if !jumpscare_texture.finished()
+        {
+            camera.add_trauma(0.1);
+        }
+        camera.update();
+
+        jumpscare_texture.sprite.dst = camera.rect_to_screen(Rect::new(0, 0, 64, 64));
         jumpscare_texture.draw(video.canvas_mut());
 
+        audio.update_captions();
+        if settings.captions
+        {
+            captions.draw(video.canvas_mut(), audio);
+        }
+
         video.canvas_mut().present();
         
         cnt += 0.2;
         if cnt > 30.0
         {
-            *state = 1;
+            *state = 0;
             break;
         }
 
         event.loop_end();
     }
+
+    //The scream's caption shouldn't carry over into the menu or the next round
+    audio.clear_captions();
 }
\ No newline at end of file
@@ -0,0 +1,175 @@
+use sdl2::{rect::{Rect, Point}, render::{BlendMode, Canvas, Texture, TextureCreator}, pixels::Color, video::{Window, WindowContext}};
+
+use crate::assets::Assets;
+use crate::engine::{Camera, PointF, Sprite};
+use crate::error::{Error, Result};
+use crate::map;
+
+//Light colours
+pub const HAND_CANDLE: Color = Color::RGB(255, 220, 170);
+pub const CANDLE: Color = Color::RGB(255, 180, 110);
+pub const MOONLIGHT: Color = Color::RGB(140, 165, 255);
+pub const GHOST: Color = Color::RGB(230, 40, 40);
+
+//Ambient light a level can be generated with, darkest first
+pub const AMBIENT_PALETTE: [Color; 4] = [Color::RGB(0, 0, 0), Color::RGB(6, 14, 10), Color::RGB(18, 6, 6), Color::RGB(10, 10, 26)];
+
+#[derive(Clone, Copy)]
+pub enum Flicker
+{
+    Steady,
+    Sine { amplitude: f64, speed: f64 } /* Radius wobbles by amplitude */
+}
+
+pub struct Light
+{
+    pub pos: PointF,
+    pub radius: f64,
+    pub color: Color,
+    pub intensity: f64, /* 0 to 1 */
+    pub flicker: Flicker
+}
+
+impl Light
+{
+    pub fn new(pos: PointF, radius: f64) -> Light
+    {
+        Light { pos, radius, color: Color::WHITE, intensity: 1.0, flicker: Flicker::Steady }
+    }
+
+    pub fn radius_at(&self, timer: f64) -> f64
+    {
+        match self.flicker
+        {
+            Flicker::Steady => self.radius,
+            Flicker::Sine { amplitude, speed } => self.radius + (timer * speed).sin() * amplitude
+        }
+    }
+}
+
+pub struct Lighting<'a>
+{
+    pub ambient: Color, /* What's left where no light reaches */
+
+    texture: Texture<'a>, /* Multiplied over the scene */
+    shadow_texture: Texture<'a>, /* Each light is drawn and shadowed here before being added to the texture */
+    sprite: Sprite<'a>
+}
+
+impl<'a> Lighting<'a>
+{
+    pub fn new(texture_creator: &'a TextureCreator<WindowContext>, assets: &Assets<'a>) -> Result<Lighting<'a>>
+    {
+        let mut texture = texture_creator.create_texture_target(None, 64, 64).map_err(|err| Error::Video(err.to_string()))?;
+        texture.set_blend_mode(BlendMode::Mod);
+
+        let mut shadow_texture = texture_creator.create_texture_target(None, 64, 64).map_err(|err| Error::Video(err.to_string()))?;
+        shadow_texture.set_blend_mode(BlendMode::Add);
+
+        let mut sprite = assets.sprite("light");
+        sprite.set_blend_mod(BlendMode::Add);
+
+        Ok(Lighting { ambient: Color::BLACK, texture, shadow_texture, sprite })
+    }
+
+    //Renders the lights into the light texture, every light is occluded by the map.
+    //Fails if the render targets were lost, the texture is only good for drawing after an Ok
+    pub fn render(&mut self, canvas: &mut Canvas<Window>, map: &[u8; 4096], lights: &[Light], camera: &Camera, timer: f64) -> Result<()>
+    {
+        let ambient = self.ambient;
+        canvas.with_texture_canvas(&mut self.texture, |canvas|
+        {
+            canvas.set_draw_color(ambient);
+            canvas.clear();
+        }).map_err(|err| Error::Video(err.to_string()))?;
+
+        canvas.set_draw_color(Color::BLACK);
+
+        for light in lights
+        {
+            let radius = light.radius_at(timer);
+            let shadows = match shadow_rects(map, &light.pos, radius, camera)
+            {
+                Some(shadows) => shadows,
+                None => continue
+            };
+
+            self.sprite.dst = camera.rect_to_screen(Rect::new(
+                light.pos.x as i32 - radius as i32,
+                light.pos.y as i32 - radius as i32,
+                (radius * 2.0) as u32,
+                (radius * 2.0) as u32
+            ));
+            self.sprite.alpha = (light.intensity.clamp(0.0, 1.0) * 255.0) as u8;
+            self.sprite.set_color_mod(light.color);
+
+            let sprite = &mut self.sprite;
+            let mut shadowed = Ok(());
+            canvas.with_texture_canvas(&mut self.shadow_texture, |canvas|
+            {
+                canvas.clear();
+                sprite.draw(canvas);
+                shadowed = canvas.fill_rects(&shadows);
+            }).map_err(|err| Error::Video(err.to_string()))?;
+            shadowed.map_err(Error::Video)?;
+
+            let shadow_texture = &self.shadow_texture;
+            let mut added = Ok(());
+            canvas.with_texture_canvas(&mut self.texture, |canvas|
+            {
+                added = canvas.copy(shadow_texture, None, None);
+            }).map_err(|err| Error::Video(err.to_string()))?;
+            added.map_err(Error::Video)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn draw(&self, canvas: &mut Canvas<Window>) -> Result<()>
+    {
+        canvas.copy(&self.texture, None, None).map_err(Error::Video)
+    }
+}
+
+//Screen pixels around a light that tiles hide from it as horizontal runs, None if the light is off screen
+fn shadow_rects(map: &[u8; 4096], pos: &PointF, radius: f64, camera: &Camera) -> Option<Vec<Rect>>
+{
+    let top_left = camera.world_to_screen(&PointF { x: pos.x - radius, y: pos.y - radius });
+    let bottom_right = camera.world_to_screen(&PointF { x: pos.x + radius, y: pos.y + radius });
+    let left = top_left.x().max(0);
+    let right = (bottom_right.x() + 1).min(64);
+    let top = top_left.y().max(0);
+    let bottom = (bottom_right.y() + 1).min(64);
+
+    if left >= right || top >= bottom
+    {
+        return None;
+    }
+
+    let polygon = map::visibility_polygon(map, pos, radius);
+    let mut rects = Vec::new();
+
+    for y in top..bottom
+    {
+        let mut run_start = None;
+        for x in left..=right
+        {
+            let world = camera.screen_to_world(Point::new(x, y));
+            let pixel = PointF { x: world.x + 0.5, y: world.y + 0.5 };
+            let shadowed = x < right && !map::polygon_contains(&polygon, &pixel);
+
+            match (shadowed, run_start)
+            {
+                (true, None) => run_start = Some(x),
+                (false, Some(start)) =>
+                {
+                    rects.push(Rect::new(start, y, (x - start) as u32, 1));
+                    run_start = None;
+                },
+                _ => {}
+            }
+        }
+    }
+
+    Some(rects)
+}
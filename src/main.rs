@@ -1,30 +1,84 @@
 #![windows_subsystem = "windows"]
-use engine::{Init, Video, EventLoop, Audio};
+use assets::Assets;
+use engine::{Init, Video, EventLoop, Audio, Bus, Renderer};
+use error::Result;
 use game::game;
 use jumpscare::jumpscare;
+use menu::menu;
+use settings::{Settings, SETTINGS_PATH};
 
+mod assets;
+mod captions;
+mod difficulty;
 mod engine;
+mod error;
 mod game;
 mod jumpscare;
+mod lighting;
+mod map;
+mod menu;
+mod noise;
+mod particles;
+mod settings;
+mod soundtrack;
+mod tips;
 
 pub fn main() 
+{
+    if let Err(err) = run()
+    {
+        error::report(&err);
+    }
+}
+
+fn run() -> Result<()>
 {
     //Engine stuff
-    let init = Init::new();
-    let mut video = Video::new(init.context(), 64, 64);
-    let mut event = EventLoop::new(init.context(), 60);
-    let audio = Audio::new();
+    let mut settings = Settings::load(SETTINGS_PATH);
+
+    //Command line flags override the settings for this run only
+    let args: Vec<String> = std::env::args().collect();
+    let no_audio = args.iter().any(|arg| arg == "--no-audio");
+    let renderer = match args.iter().position(|arg| arg == "--renderer").and_then(|i| args.get(i + 1))
+    {
+        Some(name) => Renderer::from_name(name).unwrap_or_else(||
+        {
+            eprintln!("Warning: unknown renderer {}, expected auto, accelerated or software", name);
+            settings.renderer
+        }),
+        None => settings.renderer
+    };
+
+    let init = Init::new()?;
+    let mut video = Video::new(init.context(), 64, 64, renderer)?;
+    let mut event = EventLoop::new(init.context(), 60)?;
+    let mut audio = Audio::new(!no_audio);
+    for bus in Bus::ALL
+    {
+        audio.set_bus_volume(bus, settings.volumes[bus.index()]);
+    }
+
     let texture_creator = video.canvas().texture_creator();
-    video.canvas_mut().window_mut().set_size(640, 640).unwrap();
+    let _ = video.canvas_mut().window_mut().set_size(640, 640);
     video.canvas_mut().window_mut().maximize();
-    
-    let mut state = 1;
+
+    //Missing files have placeholders, so they're only logged
+    let assets = Assets::load(&texture_creator, &audio)?;
+    for err in assets.missing()
+    {
+        eprintln!("Warning: {}", err);
+    }
+
+    let mut state = 0;
     'state_loop: loop {
         match state {
             -1 => break 'state_loop,
-            1 => game(&mut video, &audio, &mut event, &texture_creator, &mut state),
-            2 => jumpscare(&mut video, &audio, &mut event, &texture_creator, &mut state),
+            0 => menu(&mut video, &mut audio, &mut event, &assets, &mut state, &mut settings),
+            1 => game(&mut video, &mut audio, &mut event, &texture_creator, &assets, &mut state, &mut settings)?,
+            2 => jumpscare(&mut video, &mut audio, &mut event, &assets, &mut state, &settings),
             _ => {}
         }
     }
+
+    Ok(())
 }
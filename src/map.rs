@@ -0,0 +1,402 @@
+use sdl2::pixels::Color;
+
+use crate::engine::PointF;
+
+pub const MAP_SIZE: usize = 64;
+pub const TILE_SIZE: f64 = 16.0;
+
+//Tiles
+pub const EMPTY: u8 = 0;
+pub const TREE: u8 = 1;
+pub const FENCE: u8 = 2;
+pub const WARDROBE: u8 = 3;
+
+//What a tile sounds like underfoot
+#[derive(Clone, Copy)]
+pub enum Surface
+{
+    Dirt,
+    Leaves,
+    Wood
+}
+
+impl Surface
+{
+    pub fn name(self) -> &'static str
+    {
+        match self
+        {
+            Surface::Dirt => "dirt",
+            Surface::Leaves => "leaves",
+            Surface::Wood => "wood"
+        }
+    }
+
+    //Multiplies how far footsteps carry
+    pub fn loudness(self) -> f64
+    {
+        match self
+        {
+            Surface::Dirt => 1.0,
+            Surface::Leaves => 1.25,
+            Surface::Wood => 1.1
+        }
+    }
+}
+
+pub struct Sight
+{
+    pub visible: bool,
+    pub blocker: Option<(usize, usize)> /* First tile in the way */
+}
+
+//Tile at a world position, outside the map counts as empty
+pub fn tile_at(map: &[u8; 4096], x: f64, y: f64) -> u8
+{
+    if x < 0.0 || y < 0.0
+    {
+        return EMPTY;
+    }
+
+    let (x, y) = ((x / TILE_SIZE) as usize, (y / TILE_SIZE) as usize);
+    if x >= MAP_SIZE || y >= MAP_SIZE
+    {
+        return EMPTY;
+    }
+
+    map[x + y * MAP_SIZE]
+}
+
+pub fn is_solid(tile: u8) -> bool
+{
+    tile == FENCE
+}
+
+//Fences and wardrobes block sight and light
+pub fn is_opaque(tile: u8) -> bool
+{
+    tile == FENCE || tile == WARDROBE
+}
+
+//Whether any corner of the box is inside a solid tile
+pub fn box_hits_solid(map: &[u8; 4096], x: f64, y: f64, w: f64, h: f64) -> bool
+{
+    [(x, y), (x + w, y), (x, y + h), (x + w, y + h)].iter().any(|(x, y)| is_solid(tile_at(map, *x, *y)))
+}
+
+//Walks the tiles a line crosses (DDA) until an opaque one, returns it and how far along the line (0 to 1) it starts.
+//The tile the line starts in never blocks, the one it ends in only does if `include_end` is set
+fn first_opaque(map: &[u8; 4096], from: &PointF, to: &PointF, include_end: bool) -> Option<(usize, usize, f64)>
+{
+    let (dx, dy) = (to.x - from.x, to.y - from.y);
+    let (mut x, mut y) = ((from.x / TILE_SIZE).floor() as i32, (from.y / TILE_SIZE).floor() as i32);
+    let (end_x, end_y) = ((to.x / TILE_SIZE).floor() as i32, (to.y / TILE_SIZE).floor() as i32);
+
+    let step_x = if dx > 0.0 { 1 } else { -1 };
+    let step_y = if dy > 0.0 { 1 } else { -1 };
+
+    //How far along the line each tile border is
+    let delta_x = if dx == 0.0 { f64::INFINITY } else { (TILE_SIZE / dx).abs() };
+    let delta_y = if dy == 0.0 { f64::INFINITY } else { (TILE_SIZE / dy).abs() };
+    let mut t_x = if dx == 0.0 { f64::INFINITY } else { ((x + (step_x + 1) / 2) as f64 * TILE_SIZE - from.x) / dx };
+    let mut t_y = if dy == 0.0 { f64::INFINITY } else { ((y + (step_y + 1) / 2) as f64 * TILE_SIZE - from.y) / dy };
+
+    //Tiles off the map never block, and neither does the end unless asked
+    let blocks = |x: i32, y: i32| (include_end || (x, y) != (end_x, end_y)) && x >= 0 && y >= 0 && x < MAP_SIZE as i32 && y < MAP_SIZE as i32
+        && is_opaque(map[x as usize + y as usize * MAP_SIZE]);
+
+    while (x, y) != (end_x, end_y) && t_x.min(t_y) <= 1.0
+    {
+        let t;
+        if t_x == t_y
+        {
+            //Exactly through a corner, it only gets past if one of the tiles either side is open.
+            //Checking both keeps the answer the same whichever way the line goes
+            t = t_x;
+            if blocks(x + step_x, y) && blocks(x, y + step_y)
+            {
+                return Some(((x + step_x) as usize, y as usize, t));
+            }
+
+            x += step_x;
+            y += step_y;
+            t_x += delta_x;
+            t_y += delta_y;
+        }
+        else if t_x < t_y
+        {
+            x += step_x;
+            t = t_x;
+            t_x += delta_x;
+        }
+        else
+        {
+            y += step_y;
+            t = t_y;
+            t_y += delta_y;
+        }
+
+        if (x, y) == (end_x, end_y) && !include_end
+        {
+            break;
+        }
+
+        if blocks(x, y)
+        {
+            return Some((x as usize, y as usize, t));
+        }
+    }
+
+    None
+}
+
+//Whether two points can see each other, the tiles the points are in don't block
+pub fn line_of_sight(map: &[u8; 4096], from: &PointF, to: &PointF) -> Sight
+{
+    match first_opaque(map, from, to, false)
+    {
+        Some((x, y, _)) => Sight { visible: false, blocker: Some((x, y)) },
+        None => Sight { visible: true, blocker: None }
+    }
+}
+
+//Where a ray stops, either on the first opaque tile or at its full length
+pub fn cast_ray(map: &[u8; 4096], from: &PointF, angle: f64, length: f64) -> PointF
+{
+    let to = PointF { x: from.x + angle.cos() * length, y: from.y + angle.sin() * length };
+
+    match first_opaque(map, from, &to, true)
+    {
+        Some((_, _, t)) =>
+        {
+            //Go a little into the tile so its face catches some light
+            let t = (t + 2.0 / length).min(1.0);
+            PointF { x: from.x + (to.x - from.x) * t, y: from.y + (to.y - from.y) * t }
+        },
+        None => to
+    }
+}
+
+//Area lit by a light, as a polygon sorted by angle around it
+pub fn visibility_polygon(map: &[u8; 4096], origin: &PointF, radius: f64) -> Vec<PointF>
+{
+    //A ring of rays so the polygon follows the light's circle where nothing's in the way
+    let mut angles: Vec<f64> = (0..32).map(|i| i as f64 / 32.0 * std::f64::consts::TAU).collect();
+
+    //Rays just either side of every nearby opaque tile corner
+    let tiles = (radius / TILE_SIZE).ceil() as i32 + 1;
+    let (cx, cy) = ((origin.x / TILE_SIZE) as i32, (origin.y / TILE_SIZE) as i32);
+    for y in (cy - tiles)..=(cy + tiles)
+    {
+        for x in (cx - tiles)..=(cx + tiles)
+        {
+            if x < 0 || y < 0 || x >= MAP_SIZE as i32 || y >= MAP_SIZE as i32 || !is_opaque(map[x as usize + y as usize * MAP_SIZE])
+            {
+                continue;
+            }
+
+            for (corner_x, corner_y) in [(x, y), (x + 1, y), (x, y + 1), (x + 1, y + 1)]
+            {
+                let angle = (corner_y as f64 * TILE_SIZE - origin.y).atan2(corner_x as f64 * TILE_SIZE - origin.x);
+                angles.push(angle - 0.001);
+                angles.push(angle + 0.001);
+            }
+        }
+    }
+
+    //Sort around the light, atan2 gives -PI..PI but the ring is 0..TAU
+    angles.iter_mut().for_each(|angle| *angle = angle.rem_euclid(std::f64::consts::TAU));
+    angles.sort_by(|a, b| a.total_cmp(b));
+
+    angles.iter().map(|angle| cast_ray(map, origin, *angle, radius)).collect()
+}
+
+//Even-odd test
+pub fn polygon_contains(polygon: &[PointF], point: &PointF) -> bool
+{
+    let mut inside = false;
+    let mut j = polygon.len().wrapping_sub(1);
+
+    for i in 0..polygon.len()
+    {
+        let (a, b) = (&polygon[i], &polygon[j]);
+        if (a.y > point.y) != (b.y > point.y) && point.x < (b.x - a.x) * (point.y - a.y) / (b.y - a.y) + a.x
+        {
+            inside = !inside;
+        }
+
+        j = i;
+    }
+
+    inside
+}
+
+//Marks tiles around the point that can be seen from it
+pub fn reveal(map: &[u8; 4096], explored: &mut [bool; 4096], from: &PointF, radius: f64)
+{
+    let tiles = (radius / TILE_SIZE).ceil() as i32;
+    let (cx, cy) = ((from.x / TILE_SIZE) as i32, (from.y / TILE_SIZE) as i32);
+
+    for y in (cy - tiles)..=(cy + tiles)
+    {
+        for x in (cx - tiles)..=(cx + tiles)
+        {
+            if x < 0 || y < 0 || x >= MAP_SIZE as i32 || y >= MAP_SIZE as i32
+            {
+                continue;
+            }
+
+            let tile_center = PointF { x: (x as f64 + 0.5) * TILE_SIZE, y: (y as f64 + 0.5) * TILE_SIZE };
+            if from.distance(&tile_center) > radius
+            {
+                continue;
+            }
+
+            //The wall in the way is seen even if the tile behind it isn't
+            match line_of_sight(map, from, &tile_center).blocker
+            {
+                Some((bx, by)) => explored[bx + by * MAP_SIZE] = true,
+                None => explored[x as usize + y as usize * MAP_SIZE] = true
+            }
+        }
+    }
+}
+
+pub fn surface(tile: u8) -> Surface
+{
+    match tile
+    {
+        TREE => Surface::Leaves,
+        WARDROBE => Surface::Wood,
+        _ => Surface::Dirt
+    }
+}
+
+//Where the tile is in objects.png
+pub fn tile_src_x(tile: u8) -> i32
+{
+    match tile
+    {
+        WARDROBE => 96,
+        tile => (tile as i32 - 1) * 16
+    }
+}
+
+pub fn tile_color(tile: u8) -> Color
+{
+    match tile
+    {
+        TREE => Color::RGB(0, 94, 41),
+        FENCE => Color::RGB(94, 25, 0),
+        WARDROBE => Color::RGB(150, 90, 40),
+        _ => Color::RGB(20, 20, 20)
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    //A map with the given tiles, everything else empty
+    fn build(tiles: &[(usize, usize, u8)]) -> [u8; 4096]
+    {
+        let mut map = [EMPTY; 4096];
+        for (x, y, tile) in tiles
+        {
+            map[x + y * MAP_SIZE] = *tile;
+        }
+        map
+    }
+
+    //Middle of a tile in world space
+    fn center(x: usize, y: usize) -> PointF
+    {
+        PointF { x: (x as f64 + 0.5) * TILE_SIZE, y: (y as f64 + 0.5) * TILE_SIZE }
+    }
+
+    #[test]
+    fn fence_blocks_a_horizontal_line()
+    {
+        let map = build(&[(3, 2, FENCE)]);
+        let sight = line_of_sight(&map, &center(1, 2), &center(5, 2));
+
+        assert!(!sight.visible);
+        assert_eq!(sight.blocker, Some((3, 2)));
+    }
+
+    #[test]
+    fn fence_blocks_the_reverse_line()
+    {
+        let map = build(&[(3, 2, FENCE)]);
+        let sight = line_of_sight(&map, &center(5, 2), &center(1, 2));
+
+        assert!(!sight.visible);
+        assert_eq!(sight.blocker, Some((3, 2)));
+    }
+
+    #[test]
+    fn diagonal_through_a_corner()
+    {
+        //Two fences touching at a corner leave no crack to see through
+        let closed = build(&[(3, 2, FENCE), (2, 3, FENCE)]);
+        assert!(!line_of_sight(&closed, &center(2, 2), &center(3, 3)).visible);
+
+        assert!(!line_of_sight(&closed, &center(3, 3), &center(2, 2)).visible);
+
+        //With one of them gone the line squeezes past the other's corner, both ways
+        let open = build(&[(3, 2, FENCE)]);
+        assert!(line_of_sight(&open, &center(2, 2), &center(3, 3)).visible);
+        assert!(line_of_sight(&open, &center(3, 3), &center(2, 2)).visible);
+    }
+
+    #[test]
+    fn end_inside_an_opaque_tile()
+    {
+        let map = build(&[(3, 2, FENCE)]);
+
+        //The tile being looked at doesn't hide itself
+        assert!(line_of_sight(&map, &center(1, 2), &center(3, 2)).visible);
+
+        //A ray stops just inside it
+        let hit = cast_ray(&map, &center(1, 2), 0.0, 64.0);
+        assert!(hit.x >= 48.0 && hit.x <= 52.0, "{}", hit.x);
+        assert_eq!(hit.y, center(1, 2).y);
+    }
+
+    #[test]
+    fn line_leaving_the_map()
+    {
+        let map = build(&[(3, 2, FENCE)]);
+        let from = center(0, 0);
+
+        assert!(line_of_sight(&map, &from, &PointF { x: -100.0, y: -50.0 }).visible);
+
+        let hit = cast_ray(&map, &from, std::f64::consts::PI, 100.0);
+        assert!((hit.x - (from.x - 100.0)).abs() < 1e-9);
+        assert!((hit.y - from.y).abs() < 1e-9);
+    }
+
+    #[test]
+    fn light_stops_at_a_fence()
+    {
+        let map = build(&(0..8).map(|y| (3, y, FENCE)).collect::<Vec<_>>());
+        let light = center(1, 4);
+        let polygon = visibility_polygon(&map, &light, 60.0);
+
+        assert!(polygon_contains(&polygon, &center(2, 4)));
+        assert!(!polygon_contains(&polygon, &center(4, 4)));
+    }
+
+    #[test]
+    fn reveal_marks_the_blocker_not_behind_it()
+    {
+        let map = build(&[(3, 2, FENCE)]);
+        let mut explored = [false; 4096];
+        reveal(&map, &mut explored, &center(1, 2), 64.0);
+
+        assert!(explored[1 + 2 * MAP_SIZE]);
+        assert!(explored[3 + 2 * MAP_SIZE]);
+        assert!(!explored[4 + 2 * MAP_SIZE]);
+    }
+}
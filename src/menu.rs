@@ -0,0 +1,94 @@
+use sdl2::{keyboard::Keycode, pixels::Color, rect::{Point, Rect}};
+
+use crate::assets::Assets;
+use crate::difficulty::Preset;
+use crate::engine::{Align, Bus, Priority, TextBox, Video, Audio, EventLoop};
+use crate::settings::{Settings, SETTINGS_PATH};
+
+const HELP: &str = "Arrows walk, X runs, Z lights a candle, C hides in a wardrobe, A shows the map. Here R shows the tips again and C turns captions on or off.";
+
+pub fn menu(video: &mut Video, audio: &mut Audio, event: &mut EventLoop, assets: &Assets, state: &mut i32, settings: &mut Settings)
+{
+    let mut back = assets.sprite("back");
+    let mut pixel = assets.sprite("pixel");
+    let mut font = assets.font();
+
+    let mut help = TextBox::new(&font, Rect::new(2, 44, 60, 18), HELP);
+    help.color = Color::RGB(120, 140, 125);
+    help.speed = 0.2;
+
+    let mut selected = settings.preset.index();
+    loop
+    {
+        if !event.loop_start() { *state = -1; break; }
+        video.canvas_mut().clear();
+
+        //Pick difficulty
+        if event.is_key_down(Keycode::Left) && selected > 0
+        {
+            selected -= 1;
+        }
+        else if event.is_key_down(Keycode::Right) && selected < Preset::ALL.len() - 1
+        {
+            selected += 1;
+        }
+
+        //Show the tips again
+        if event.is_key_down(Keycode::R) && !settings.seen_tips.is_empty()
+        {
+            settings.seen_tips.clear();
+            settings.save(SETTINGS_PATH);
+            audio.play(assets.sound("tip"), Bus::Sfx, Priority::High, 0);
+        }
+
+        //Captions for the game's sounds
+        if event.is_key_down(Keycode::C)
+        {
+            settings.captions = !settings.captions;
+            settings.save(SETTINGS_PATH);
+        }
+
+        if event.is_key_down(Keycode::Z)
+        {
+            settings.preset = Preset::ALL[selected];
+            settings.save(SETTINGS_PATH);
+
+            *state = 1;
+            break;
+        }
+
+        help.update();
+
+        back.draw(video.canvas_mut());
+
+        font.draw_text(video.canvas_mut(), Point::new(32, 4), "Difficulty", Color::RGB(120, 140, 125), Align::Center);
+        font.draw_text(video.canvas_mut(), Point::new(32, 12), Preset::ALL[selected].name(), Color::WHITE, Align::Center);
+        font.draw_text(video.canvas_mut(), Point::new(32, 24), if settings.captions { "Captions on" } else { "Captions off" }, Color::RGB(120, 140, 125), Align::Center);
+        font.draw_text(video.canvas_mut(), Point::new(32, 34), "Z to start", Color::RED, Align::Center);
+        help.draw(video.canvas_mut(), &mut font);
+
+        //Arrows
+        pixel.set_color_mod(Color::RED);
+        pixel.dst.set_width(1);
+        for i in 0..3
+        {
+            pixel.dst.set_height(5 - i as u32 * 2);
+            pixel.dst.set_y(12 + i);
+
+            if selected > 0
+            {
+                pixel.dst.set_x(3 + i);
+                pixel.draw(video.canvas_mut());
+            }
+
+            if selected < Preset::ALL.len() - 1
+            {
+                pixel.dst.set_x(60 - i);
+                pixel.draw(video.canvas_mut());
+            }
+        }
+
+        video.canvas_mut().present();
+        event.loop_end();
+    }
+}
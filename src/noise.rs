@@ -0,0 +1,26 @@
+use crate::engine::PointF;
+
+//How far each kind of sound carries
+pub const MATCH_STRIKE: f64 = 48.0;
+pub const CANDLE_LIT: f64 = 160.0;
+pub const BUMP: f64 = 56.0;
+pub const GASP: f64 = 64.0;
+
+pub struct Noise
+{
+    pub pos: PointF,
+    pub radius: f64
+}
+
+pub fn emit(noises: &mut Vec<Noise>, pos: PointF, radius: f64)
+{
+    noises.push(Noise { pos, radius });
+}
+
+//Noise that reaches the listener the loudest, if any
+pub fn loudest<'a>(noises: &'a [Noise], listener: &PointF) -> Option<&'a Noise>
+{
+    noises.iter()
+        .filter(|noise| noise.pos.distance(listener) < noise.radius)
+        .max_by(|a, b| (a.radius - a.pos.distance(listener)).total_cmp(&(b.radius - b.pos.distance(listener))))
+}
@@ -0,0 +1,146 @@
+use rand::{rngs::ThreadRng, Rng};
+use sdl2::pixels::Color;
+
+use crate::engine::PointF;
+
+pub struct Emitter
+{
+    pub rate: f64, /* Particles per frame, fractions are rolled */
+    pub life: f64, /* Frames */
+    pub spread: f64, /* Random offset from the emitter */
+    pub vel_x: (f64, f64),
+    pub vel_y: (f64, f64),
+    pub gravity: f64,
+    pub colors: (Color, Color), /* Fades from the first to the second over its life */
+    pub light: f64 /* Radius of the light it gives off, 0 for none */
+}
+
+//Emitters
+pub static SMOKE: Emitter = Emitter
+{
+    rate: 0.5, life: 55.0, spread: 1.0,
+    vel_x: (-0.05, 0.05), vel_y: (-0.75, -0.65), gravity: 0.0,
+    colors: (Color::RGB(128, 128, 128), Color::RGB(48, 48, 48)),
+    light: 0.0
+};
+
+pub static SPARKS: Emitter = Emitter
+{
+    rate: 0.02, life: 40.0, spread: 1.0,
+    vel_x: (-0.2, 0.2), vel_y: (-0.9, -0.5), gravity: 0.01,
+    colors: (Color::RGB(255, 255, 0), Color::RGB(255, 80, 0)),
+    light: 3.0
+};
+
+pub static GHOST_TRAIL: Emitter = Emitter
+{
+    rate: 0.2, life: 55.0, spread: 1.0,
+    vel_x: (-0.1, 0.1), vel_y: (-0.7, -0.5), gravity: 0.0,
+    colors: (Color::RGB(255, 0, 0), Color::RGB(90, 0, 0)),
+    light: 3.0
+};
+
+pub static DUST: Emitter = Emitter
+{
+    rate: 0.03, life: 120.0, spread: 8.0,
+    vel_x: (-0.05, 0.05), vel_y: (0.02, 0.08), gravity: 0.0,
+    colors: (Color::RGB(200, 210, 255), Color::RGB(60, 70, 100)),
+    light: 0.0
+};
+
+pub struct Particle
+{
+    pub pos: PointF,
+    pub vel: PointF,
+    pub age: f64,
+    pub alive: bool,
+    pub emitter: &'static Emitter
+}
+
+impl Particle
+{
+    //Frames until it dies, emitters give their particles different lives
+    pub fn life_left(&self) -> f64
+    {
+        self.emitter.life - self.age
+    }
+
+    pub fn color(&self) -> Color
+    {
+        let t = (self.age / self.emitter.life).clamp(0.0, 1.0);
+        let (from, to) = self.emitter.colors;
+        let lerp = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * t) as u8;
+
+        Color::RGB(lerp(from.r, to.r), lerp(from.g, to.g), lerp(from.b, to.b))
+    }
+}
+
+pub struct Particles
+{
+    pool: Vec<Particle>, /* Never grows, dead particles get reused */
+    next: usize
+}
+
+impl Particles
+{
+    pub fn new(capacity: usize) -> Particles
+    {
+        let pool = (0..capacity).map(|_| Particle { pos: PointF { x: 0.0, y: 0.0 }, vel: PointF { x: 0.0, y: 0.0 }, age: 0.0, alive: false, emitter: &SMOKE }).collect();
+        Particles { pool, next: 0 }
+    }
+
+    //Spawns this frame's share of particles, scale thins or thickens the emitter's rate
+    pub fn emit(&mut self, emitter: &'static Emitter, pos: PointF, scale: f64, rand: &mut ThreadRng)
+    {
+        let rate = emitter.rate * scale;
+        let mut count = rate.floor() as usize;
+        if rand.gen_bool(rate.fract())
+        {
+            count += 1;
+        }
+
+        self.burst(emitter, pos, count, rand);
+    }
+
+    pub fn burst(&mut self, emitter: &'static Emitter, pos: PointF, count: usize, rand: &mut ThreadRng)
+    {
+        for _i in 0..count
+        {
+            //Take the next dead slot, or the one closest to dying when the pool is full
+            let len = self.pool.len();
+            let slot = (0..len).map(|i| (self.next + i) % len).find(|i| !self.pool[*i].alive).unwrap_or_else(||
+            {
+                (0..len).min_by(|a, b| self.pool[*a].life_left().total_cmp(&self.pool[*b].life_left())).unwrap_or(self.next)
+            });
+            self.next = (slot + 1) % len;
+
+            let random = |rand: &mut ThreadRng, (min, max): (f64, f64)| if min < max { rand.gen_range(min..max) } else { min };
+            self.pool[slot] = Particle
+            {
+                pos: PointF { x: pos.x + random(rand, (-emitter.spread, emitter.spread)), y: pos.y + random(rand, (-emitter.spread, emitter.spread)) },
+                vel: PointF { x: random(rand, emitter.vel_x), y: random(rand, emitter.vel_y) },
+                age: 0.0,
+                alive: true,
+                emitter
+            };
+        }
+    }
+
+    pub fn update(&mut self)
+    {
+        for part in self.pool.iter_mut().filter(|part| part.alive)
+        {
+            part.vel.y += part.emitter.gravity;
+            part.pos.x += part.vel.x;
+            part.pos.y += part.vel.y;
+
+            part.age += 1.0;
+            part.alive = part.age < part.emitter.life;
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Particle>
+    {
+        self.pool.iter().filter(|part| part.alive)
+    }
+}
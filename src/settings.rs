@@ -0,0 +1,100 @@
+use std::fs;
+
+use crate::difficulty::{Difficulty, Preset};
+use crate::engine::{Bus, Renderer};
+use crate::tips::Tip;
+
+pub const SETTINGS_PATH: &str = "settings.cfg";
+
+pub struct Settings
+{
+    pub preset: Preset,
+    pub custom: Difficulty,
+    pub renderer: Renderer,
+    pub volumes: [f64; 4], /* Per audio bus, 0 to 1 */
+    pub seen_tips: Vec<Tip>,
+    pub captions: bool
+}
+
+impl Settings
+{
+    //Loads "key=value" lines, missing file or unknown keys fall back to defaults
+    pub fn load(path: &str) -> Settings
+    {
+        let mut settings = Settings { preset: Preset::Normal, custom: Difficulty::from_preset(Preset::Custom), renderer: Renderer::Auto, volumes: [1.0; 4], seen_tips: Vec::new(), captions: false };
+
+        let text = match fs::read_to_string(path)
+        {
+            Ok(text) => text,
+            Err(_) => return settings
+        };
+
+        for line in text.lines()
+        {
+            let (key, value) = match line.split_once('=')
+            {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => continue
+            };
+
+            if key == "difficulty"
+            {
+                settings.preset = Preset::from_name(value).unwrap_or(Preset::Normal);
+            }
+            else if key == "renderer"
+            {
+                settings.renderer = Renderer::from_name(value).unwrap_or(Renderer::Auto);
+            }
+            else if key == "captions"
+            {
+                settings.captions = value == "on";
+            }
+            else if key == "seen_tips"
+            {
+                settings.seen_tips = value.split(',').filter_map(|name| Tip::from_name(name.trim())).collect();
+            }
+            else if let Some(bus) = key.strip_prefix("volume.").and_then(|name| Bus::ALL.into_iter().find(|bus| bus.name() == name))
+            {
+                settings.volumes[bus.index()] = value.parse::<f64>().unwrap_or(1.0).clamp(0.0, 1.0);
+            }
+            else if let Some(key) = key.strip_prefix("custom.")
+            {
+                settings.custom.set(key, value);
+            }
+        }
+
+        settings
+    }
+
+    pub fn save(&self, path: &str)
+    {
+        let mut text = format!("difficulty={}\nrenderer={}\n", self.preset.name(), self.renderer.name());
+
+        text += &format!("captions={}\n", if self.captions { "on" } else { "off" });
+
+        let seen: Vec<&str> = self.seen_tips.iter().map(|tip| tip.name()).collect();
+        text += &format!("seen_tips={}\n", seen.join(","));
+
+        for bus in Bus::ALL
+        {
+            text += &format!("volume.{}={}\n", bus.name(), self.volumes[bus.index()]);
+        }
+
+        for (key, value) in self.custom.values()
+        {
+            text += &format!("custom.{}={}\n", key, value);
+        }
+
+        //Not being able to save settings shouldn't stop the game
+        let _ = fs::write(path, text);
+    }
+
+    pub fn difficulty(&self) -> Difficulty
+    {
+        match self.preset
+        {
+            Preset::Custom => self.custom.clone(),
+            preset => Difficulty::from_preset(preset)
+        }
+    }
+}
@@ -0,0 +1,73 @@
+use rand::{rngs::ThreadRng, Rng};
+
+use crate::assets::Assets;
+use crate::engine::{Audio, Bus, Priority, Voice};
+
+//Full volume of each layer
+const CALM_VOLUME: f64 = 0.25;
+const TENSE_VOLUME: f64 = 0.4;
+
+//How fast threat and the layers follow the game, per frame
+const SMOOTHING: f64 = 0.01;
+
+const STINGERS: [&str; 2] = ["scary", "strange"];
+
+//Loops that crossfade with the threat level, plus stingers when it's high
+pub struct Soundtrack
+{
+    pub threat: f64, /* 0 to 1, smoothed */
+
+    calm: Voice,
+    tense: Voice,
+    cooldown: u32, /* Frames until the next stinger is allowed */
+    last_stinger: usize
+}
+
+impl Soundtrack
+{
+    pub fn start(audio: &mut Audio, assets: &Assets) -> Soundtrack
+    {
+        audio.play_music(assets.music("fire_ambient"), -1);
+
+        //Both layers of the crossfade are ambience, so one volume setting covers the pair
+        let calm = audio.play(assets.sound("ambient"), Bus::Ambience, Priority::High, -1);
+        audio.set_volume(calm, CALM_VOLUME);
+
+        let tense = audio.play(assets.sound("ambient_tense"), Bus::Ambience, Priority::High, -1);
+        audio.set_volume(tense, 0.0);
+
+        Soundtrack { threat: 0.0, calm, tense, cooldown: 600, last_stinger: 0 }
+    }
+
+    pub fn update(&mut self, audio: &mut Audio, assets: &Assets, threat: f64, rand: &mut ThreadRng)
+    {
+        self.threat += (threat.clamp(0.0, 1.0) - self.threat) * SMOOTHING;
+
+        //Calm fades out as the tense layer comes in
+        audio.set_volume(self.calm, CALM_VOLUME * (1.0 - self.threat));
+        audio.set_volume(self.tense, TENSE_VOLUME * self.threat);
+
+        //Stingers, never the same one twice in a row
+        if self.cooldown > 0
+        {
+            self.cooldown -= 1;
+        }
+        else if rand.gen_bool(self.threat * 0.004)
+        {
+            let stinger = (self.last_stinger + rand.gen_range(1..STINGERS.len())) % STINGERS.len();
+            //Stingers are part of the score rather than the world, so they follow the music volume
+            audio.play(assets.sound(STINGERS[stinger]), Bus::Music, Priority::Normal, 0);
+
+            self.last_stinger = stinger;
+            self.cooldown = rand.gen_range(300..900);
+        }
+    }
+
+    //Everything, music included, fades out and the loops free their channels
+    pub fn fade_out(&self, audio: &Audio, ms: i32)
+    {
+        audio.fade_out(self.calm, ms);
+        audio.fade_out(self.tense, ms);
+        audio.fade_out_music(ms);
+    }
+}
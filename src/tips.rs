@@ -0,0 +1,105 @@
+use sdl2::{rect::{Point, Rect}, pixels::Color, render::Canvas, video::Window};
+
+use crate::assets::Assets;
+use crate::engine::{Align, Audio, Bus, Font, Priority, Sprite};
+use crate::settings::Settings;
+
+//Frames a tip stays on screen
+const TIP_FRAMES: u32 = 240;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Tip
+{
+    Candle, /* First candle in reach */
+    Ghost, /* First time the ghost is seen */
+    Dark /* First time the dark gets scary */
+}
+
+impl Tip
+{
+    pub const ALL: [Tip; 3] = [Tip::Candle, Tip::Ghost, Tip::Dark];
+
+    pub fn name(self) -> &'static str
+    {
+        match self
+        {
+            Tip::Candle => "candle",
+            Tip::Ghost => "ghost",
+            Tip::Dark => "dark"
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Tip>
+    {
+        Tip::ALL.into_iter().find(|t| t.name() == name)
+    }
+
+    pub fn text(self) -> &'static str
+    {
+        match self
+        {
+            Tip::Candle => "Hold Z to light the candle",
+            Tip::Ghost => "Run with X, hide with C",
+            Tip::Dark => "Fear the dark. A shows the map"
+        }
+    }
+}
+
+//Hints shown once each, the ones already seen are kept in the settings
+pub struct Tips<'a>
+{
+    queue: Vec<Tip>,
+    showing: Option<(Tip, u32)>, /* Tip and frames left */
+    font: Font<'a>,
+    back: Sprite<'a>
+}
+
+impl<'a> Tips<'a>
+{
+    pub fn new(assets: &Assets<'a>) -> Tips<'a>
+    {
+        let mut back = assets.sprite("pixel");
+        back.set_color_mod(Color::BLACK);
+        back.alpha = 200;
+
+        Tips { queue: Vec::new(), showing: None, font: assets.font(), back }
+    }
+
+    //Queues the tip unless it's been seen before
+    pub fn trigger(&mut self, tip: Tip, settings: &mut Settings)
+    {
+        if settings.seen_tips.contains(&tip)
+        {
+            return;
+        }
+
+        settings.seen_tips.push(tip);
+        self.queue.push(tip);
+    }
+
+    pub fn update(&mut self, audio: &mut Audio, assets: &Assets)
+    {
+        self.showing = match self.showing
+        {
+            Some((tip, frames)) if frames > 1 => Some((tip, frames - 1)),
+            _ if !self.queue.is_empty() =>
+            {
+                audio.play(assets.sound("tip"), Bus::Sfx, Priority::High, 0);
+                Some((self.queue.remove(0), TIP_FRAMES))
+            },
+            _ => None
+        };
+    }
+
+    pub fn draw(&mut self, canvas: &mut Canvas<Window>)
+    {
+        if let Some((tip, _)) = self.showing
+        {
+            let lines = self.font.wrap(tip.text(), 62).len() as i32;
+            self.back.dst = Rect::new(0, 5, 64, (lines * self.font.line_height() + 1) as u32);
+            self.back.draw(canvas);
+
+            self.font.draw_wrapped(canvas, Point::new(32, 6), tip.text(), 62, Color::WHITE, Align::Center);
+        }
+    }
+}